@@ -1,8 +1,17 @@
 mod plane {
+    pub mod atmosphere;
+    pub mod carrier_ops;
+    pub mod damage;
+    pub mod engine;
     pub mod flight_model;
     pub mod flight_physics;
+    pub mod fuel;
+    pub mod gear;
+    pub mod integrator;
     pub mod plane;
     pub mod plane_config;
+    pub mod table;
+    pub mod wind;
 }
 mod ai {
     pub mod dogfight_ai;
@@ -12,26 +21,108 @@ mod systems {
     pub mod enemy;
     pub mod game;
 }
+mod world {
+    pub mod carrier;
+    pub mod dem;
+    pub mod floating_origin;
+    pub mod frames;
+    pub mod geo;
+    pub mod streaming;
+    pub mod terrain;
+}
 mod util;
 
 use crate::ai::dogfight_ai::{DogfightAI, apply_ai_controls, update_dogfight_ai};
+use crate::plane::atmosphere::Atmosphere;
+use crate::plane::integrator::Integrator;
 use crate::plane::plane::Plane;
+use crate::plane::wind::{self, WindField};
 use bevy::{
     diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
     light::CascadeShadowConfigBuilder,
+    math::DVec3,
     prelude::*,
 };
 use rand::Rng;
 use systems::{aircraft, enemy, game};
+use world::carrier::{self, Carrier};
+use world::floating_origin::{self, AbsolutePosition, FloatingOrigin};
+use world::geo::GeoReference;
+use world::streaming::{self, StreamedChunks};
+use world::terrain::{self, TerrainHeight};
 
 #[derive(Component)]
 struct PlaneComponent {
     plane: Plane,
 }
 
+/// Pose written by the fixed-rate flight model. The rendered `Transform` is
+/// interpolated between `previous` and `current`.
+#[derive(Component)]
+struct PhysicsTransform {
+    previous: Transform,
+    current: Transform,
+}
+
+impl PhysicsTransform {
+    fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+}
+
 #[derive(Resource)]
 struct PlayerEntity(Entity);
 
+/// Where an aircraft is put back after it crashes
+#[derive(Component)]
+struct SpawnPoint {
+    plane_type: String,
+    /// Absolute world position
+    position: DVec3,
+    rotation: Quat,
+    velocity: Vec3,
+}
+
+/// Sent by the flight model step when an aircraft is destroyed by hitting the ground
+#[derive(Message)]
+struct PlaneCrashed {
+    entity: Entity,
+    /// Absolute world position
+    position: DVec3,
+    impact_speed: f32,
+}
+
+#[derive(Resource, Default)]
+struct Score {
+    kills: u32,
+    crashes: u32,
+}
+
+#[derive(Component)]
+struct Explosion {
+    timer: Timer,
+}
+
+/// Fixed-timestep settings for the flight model
+#[derive(Resource)]
+struct PhysicsSettings {
+    substeps: u32,
+    /// Overrides the integrator chosen in each aircraft's config
+    integrator: Option<Integrator>,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            substeps: PHYSICS_SUBSTEPS,
+            integrator: None,
+        }
+    }
+}
+
 #[derive(Component)]
 struct FpsText;
 
@@ -46,6 +137,8 @@ struct Enemy;
 
 pub const PLANE_SPAWN_POS: Vec3 = vec3(0.0, 1000.0, 0.0);
 pub const PLANE_SPAWN_VEL: Vec3 = vec3(0.0, 0.0, -200.0);
+pub const PHYSICS_RATE_HZ: f64 = 120.0;
+pub const PHYSICS_SUBSTEPS: u32 = 2;
 
 fn main() {
     App::new()
@@ -55,7 +148,27 @@ fn main() {
             // PhysicsPlugins::default(),
         ))
         // .add_plugins(AtmospherePlugin)
-        .add_systems(Startup, (aircraft::spawn_plane, game::setup))
+        .insert_resource(Time::<Fixed>::from_hz(PHYSICS_RATE_HZ))
+        .init_resource::<PhysicsSettings>()
+        .init_resource::<Atmosphere>()
+        .init_resource::<WindField>()
+        .init_resource::<TerrainHeight>()
+        .init_resource::<Score>()
+        .init_resource::<StreamedChunks>()
+        .init_resource::<FloatingOrigin>()
+        .init_resource::<GeoReference>()
+        .add_message::<PlaneCrashed>()
+        .add_systems(
+            Startup,
+            (
+                aircraft::spawn_plane,
+                carrier::spawn_carrier,
+                wind::setup_weather,
+                streaming::setup_world_assets,
+                terrain::setup_terrain.after(streaming::setup_world_assets),
+                game::setup.after(terrain::setup_terrain),
+            ),
+        )
         .add_systems(
             Startup,
             enemy::spawn_enemy_plane.after(aircraft::spawn_plane),
@@ -66,10 +179,26 @@ fn main() {
                 update_dogfight_ai, // First assess situation
                 apply_ai_controls,  // Then apply controls
                 // draw_target_vec,
-                aircraft::simulate_plane,
-                aircraft::update_plane_readings,
-                game::camera_follow,
+                aircraft::plane_input,
+                aircraft::interpolate_plane_transforms,
+                aircraft::update_plane_readings.after(aircraft::interpolate_plane_transforms),
+                game::camera_follow.after(aircraft::interpolate_plane_transforms),
                 game::update_fps,
+                game::handle_plane_crashes,
+                game::update_explosions,
+                floating_origin::recenter_origin
+                    .after(aircraft::interpolate_plane_transforms)
+                    .after(game::handle_plane_crashes)
+                    .after(game::update_explosions),
+                streaming::stream_world.after(floating_origin::recenter_origin),
+                streaming::update_lods.after(streaming::stream_world),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                carrier::move_carriers.before(aircraft::simulate_plane),
+                aircraft::simulate_plane,
             ),
         )
         .run();
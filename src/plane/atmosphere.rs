@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+
+const R_AIR: f32 = 287.052_87; // Specific gas constant for dry air, J/(kg K)
+const G0: f32 = 9.806_65; // Standard gravity, m/s^2
+const GAMMA: f32 = 1.4; // Ratio of specific heats for air
+const EARTH_RADIUS: f32 = 6_356_766.0; // Effective earth radius for geopotential altitude, m
+
+pub const SEA_LEVEL_TEMPERATURE: f32 = 288.15; // K
+pub const SEA_LEVEL_PRESSURE: f32 = 101_325.0; // Pa
+pub const SEA_LEVEL_DENSITY: f32 = 1.225; // kg/m^3
+
+/// ISA layers up to the mesopause: base geopotential altitude (m) and lapse rate (K/m)
+const LAYERS: [(f32, f32); 7] = [
+    (0.0, -0.0065),     // Troposphere
+    (11_000.0, 0.0),    // Tropopause
+    (20_000.0, 0.001),  // Stratosphere
+    (32_000.0, 0.0028), // Stratosphere
+    (47_000.0, 0.0),    // Stratopause
+    (51_000.0, -0.0028),
+    (71_000.0, -0.002),
+];
+const TOP_OF_MODEL: f32 = 84_852.0; // Geopotential altitude, m
+
+/// International Standard Atmosphere with adjustable sea-level conditions
+#[derive(Resource, Debug, Copy, Clone, Default)]
+pub struct Atmosphere {
+    /// Deviation from the standard temperature at every altitude, K
+    pub temperature_offset: f32,
+    /// Deviation from the standard sea-level pressure, Pa
+    pub pressure_offset: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct AtmosphereState {
+    pub temperature: f32,    // K
+    pub pressure: f32,       // Pa
+    pub density: f32,        // kg/m^3
+    pub speed_of_sound: f32, // m/s
+}
+
+/// Stagnation to static pressure ratio at `mach`
+#[inline(always)]
+pub fn ram_pressure_ratio(mach: f32) -> f32 {
+    ram_temperature_ratio(mach).powf(GAMMA / (GAMMA - 1.0))
+}
+
+/// Stagnation to static temperature ratio at `mach`
+#[inline(always)]
+pub fn ram_temperature_ratio(mach: f32) -> f32 {
+    1.0 + 0.5 * (GAMMA - 1.0) * mach * mach
+}
+
+#[inline(always)]
+pub fn geopotential_altitude(geometric_altitude: f32) -> f32 {
+    EARTH_RADIUS * geometric_altitude / (EARTH_RADIUS + geometric_altitude)
+}
+
+impl Atmosphere {
+    /// Samples the atmosphere at a geometric altitude above mean sea level.
+    pub fn sample(&self, altitude: f32) -> AtmosphereState {
+        let h = limit_altitude(geopotential_altitude(altitude));
+
+        let mut base_temperature = SEA_LEVEL_TEMPERATURE;
+        let mut base_pressure = SEA_LEVEL_PRESSURE + self.pressure_offset;
+        let mut standard_temperature = base_temperature;
+        let mut pressure = base_pressure;
+
+        for (i, &(base, lapse)) in LAYERS.iter().enumerate() {
+            let top = LAYERS.get(i + 1).map_or(TOP_OF_MODEL, |layer| layer.0);
+            let dh = h.min(top) - base;
+
+            standard_temperature = base_temperature + lapse * dh;
+            pressure = layer_pressure(base_pressure, base_temperature, lapse, dh);
+
+            if h <= top {
+                break;
+            }
+
+            base_temperature = standard_temperature;
+            base_pressure = pressure;
+        }
+
+        let temperature = standard_temperature + self.temperature_offset;
+
+        AtmosphereState {
+            temperature,
+            pressure,
+            density: pressure / (R_AIR * temperature),
+            speed_of_sound: (GAMMA * R_AIR * temperature).sqrt(),
+        }
+    }
+}
+
+#[inline(always)]
+fn limit_altitude(h: f32) -> f32 {
+    h.clamp(LAYERS[0].0, TOP_OF_MODEL)
+}
+
+/// Hydrostatic pressure `dh` metres above the base of a layer
+fn layer_pressure(base_pressure: f32, base_temperature: f32, lapse: f32, dh: f32) -> f32 {
+    if lapse == 0.0 {
+        base_pressure * (-G0 * dh / (R_AIR * base_temperature)).exp()
+    } else {
+        let temperature = base_temperature + lapse * dh;
+        base_pressure * (base_temperature / temperature).powf(G0 / (R_AIR * lapse))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Geometric altitude of a geopotential altitude, the inverse of `geopotential_altitude`
+    fn geometric_altitude(geopotential: f32) -> f32 {
+        EARTH_RADIUS * geopotential / (EARTH_RADIUS - geopotential)
+    }
+
+    fn assert_state(state: AtmosphereState, temperature: f32, pressure: f32, density: f32) {
+        assert!(
+            (state.temperature - temperature).abs() < 1e-2
+                && (state.pressure / pressure - 1.0).abs() < 1e-4
+                && (state.density / density - 1.0).abs() < 1e-4,
+            "expected {temperature} K, {pressure} Pa, {density} kg/m^3, found {state:?}"
+        );
+    }
+
+    #[test]
+    fn sea_level() {
+        let state = Atmosphere::default().sample(0.0);
+        assert_state(
+            state,
+            SEA_LEVEL_TEMPERATURE,
+            SEA_LEVEL_PRESSURE,
+            SEA_LEVEL_DENSITY,
+        );
+        assert!((state.speed_of_sound - 340.294).abs() < 1e-2);
+    }
+
+    #[test]
+    fn tropopause() {
+        let state = Atmosphere::default().sample(geometric_altitude(11_000.0));
+        assert_state(state, 216.65, 22_632.06, 0.363_918);
+        assert!((state.speed_of_sound - 295.070).abs() < 1e-2);
+    }
+
+    #[test]
+    fn lower_stratosphere() {
+        let state = Atmosphere::default().sample(geometric_altitude(20_000.0));
+        assert_state(state, 216.65, 5_474.89, 0.088_035);
+    }
+
+    #[test]
+    fn geometric_altitudes() {
+        // 11 km geometric is about 19 m of geopotential altitude short of the tropopause
+        let state = Atmosphere::default().sample(11_000.0);
+        assert!((state.temperature - 216.774).abs() < 1e-2, "{state:?}");
+        assert!((state.pressure / 22_700.0 - 1.0).abs() < 1e-3, "{state:?}");
+    }
+
+    #[test]
+    fn offsets() {
+        let hot = Atmosphere {
+            temperature_offset: 15.0,
+            pressure_offset: -1_000.0,
+        };
+        let state = hot.sample(0.0);
+        let pressure = SEA_LEVEL_PRESSURE - 1_000.0;
+        assert_state(state, 303.15, pressure, pressure / (R_AIR * 303.15));
+
+        let standard = Atmosphere::default().sample(geometric_altitude(20_000.0));
+        let state = hot.sample(geometric_altitude(20_000.0));
+        assert!((state.temperature - standard.temperature - 15.0).abs() < 1e-3);
+        assert!(state.density < standard.density);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::plane::flight_model::FlightModel;
+use crate::plane::flight_physics::{global_to_local, local_to_global};
+use crate::util::limit;
+use bevy::prelude::*;
+
+// The hook only catches a wire when its tip is this close to the deck
+const HOOK_CATCH_HEIGHT: f32 = 0.3; // meters
+// A wire parts when pulled this far past its runout
+const WIRE_BREAK_FACTOR: f32 = 1.5;
+// The wire tension fades out below this speed so a stopped aircraft doesn't get pulled back
+const WIRE_STOP_SPEED: f32 = 1.0; // m/s
+// Cable tension builds up over this much payout instead of snapping on at once
+const WIRE_TAKE_UP: f32 = 10.0; // meters
+
+// The nosewheel has to be this close to the shuttle, and nearly stopped, to hook up
+const CATAPULT_HOOKUP_DISTANCE: f32 = 5.0; // meters
+const CATAPULT_HOOKUP_SPEED: f32 = 3.0; // m/s
+const CATAPULT_MAX_ACCEL: f32 = 60.0; // m/s²
+
+/// Wire the tailhook has caught
+#[derive(Debug, Copy, Clone)]
+pub struct Arrest {
+    deck: usize,
+    wire: usize,
+    /// Deck point where the hook caught the wire
+    engage_point: Vec3,
+    deceleration: f32,
+}
+
+/// Catapult stroke in progress
+#[derive(Debug, Copy, Clone)]
+pub struct Launch {
+    deck: usize,
+}
+
+impl FlightModel {
+    pub fn hook_caught(&self) -> Option<usize> {
+        self.arrest.map(|arrest| arrest.wire)
+    }
+
+    pub fn launching(&self) -> bool {
+        self.launch.is_some()
+    }
+
+    /// Catches, keeps or drops an arresting wire for the hook position after a step
+    pub(crate) fn update_hook(&mut self, t: &Transform) {
+        if !self.hook_down {
+            self.arrest = None;
+            self.hook_last = None;
+            return;
+        }
+
+        let hook_point = self.plane_config.structure.hook_point;
+        let tip = local_to_global(hook_point, t.rotation) + t.translation;
+
+        if let Some(arrest) = self.arrest {
+            if self.wire_payout(arrest, tip).is_none() {
+                self.arrest = None;
+            }
+            return;
+        }
+
+        // Look for a wire crossed since the last step
+        let mut last = None;
+        for (index, deck) in self.decks.iter().enumerate() {
+            let local = deck.to_deck(tip);
+            let config = &deck.config;
+            if local.x.abs() > config.deck_width / 2.0 || local.z.abs() > config.deck_length / 2.0 {
+                continue;
+            }
+
+            if let Some((last_deck, last_local)) = self.hook_last
+                && last_deck == index
+                && local.y < HOOK_CATCH_HEIGHT
+                && local.x.abs() < config.wire_half_width
+            {
+                // Landing runs towards the bow, along -Z
+                let caught = config
+                    .wires
+                    .iter()
+                    .position(|wire| last_local.z > wire.position && local.z <= wire.position);
+                if let Some(wire) = caught {
+                    let relative = self.velocity - deck.point_velocity(tip);
+                    let speed = relative.dot(deck.forward()).max(0.0);
+                    self.arrest = Some(Arrest {
+                        deck: index,
+                        wire,
+                        engage_point: vec3(local.x, 0.0, config.wires[wire].position),
+                        deceleration: speed * speed / (2.0 * config.wires[wire].runout),
+                    });
+                    self.hook_last = None;
+                    return;
+                }
+            }
+            last = Some((index, local));
+        }
+        self.hook_last = last;
+    }
+
+    /// Cable paid out since the hook caught the wire, `None` once the wire has parted
+    fn wire_payout(&self, arrest: Arrest, tip: Vec3) -> Option<f32> {
+        let deck = self.decks.get(arrest.deck)?;
+        let payout = deck.to_deck(tip).distance(arrest.engage_point);
+        let runout = deck.config.wires[arrest.wire].runout;
+        (payout <= runout * WIRE_BREAK_FACTOR).then_some(payout)
+    }
+
+    pub(crate) fn hook_force(&mut self, t: &Transform) {
+        let Some(arrest) = self.arrest else {
+            return;
+        };
+        let hook_point = self.plane_config.structure.hook_point;
+        let tip = local_to_global(hook_point, t.rotation) + t.translation;
+        let Some(payout) = self.wire_payout(arrest, tip) else {
+            return;
+        };
+        let deck = &self.decks[arrest.deck];
+
+        // The cable pulls the hook back towards where it caught the wire
+        let anchor = deck.to_world(arrest.engage_point);
+        let direction = (anchor - tip)
+            .reject_from_normalized(deck.up())
+            .try_normalize()
+            .unwrap_or(-deck.forward());
+        let relative = self.velocity - deck.point_velocity(tip);
+        let speed = relative.dot(-direction);
+
+        let tension = self.current_mass
+            * arrest.deceleration
+            * limit(payout / WIRE_TAKE_UP, 0.0, 1.0)
+            * limit(speed / WIRE_STOP_SPEED, 0.0, 1.0);
+        let force = global_to_local(direction * tension, t.rotation);
+        self.add_local_force(force, hook_point);
+    }
+
+    /// Hooks up to a catapult on request, and lets go at the end of the stroke
+    pub(crate) fn update_catapult(&mut self, t: &Transform) {
+        let request = std::mem::take(&mut self.catapult_request);
+        let front_wheel = self.plane_config.structure.front_wheel;
+        let tow_bar = local_to_global(front_wheel, t.rotation) + t.translation;
+
+        match self.launch {
+            None if request && self.on_ground => self.launch = self.hook_up_catapult(tow_bar),
+            Some(launch) if self.catapult_remaining(launch, tow_bar).is_none() => {
+                self.launch = None
+            }
+            _ => {}
+        }
+    }
+
+    /// Stroke left ahead of the shuttle, `None` once the launch is over
+    fn catapult_remaining(&self, launch: Launch, tow_bar: Vec3) -> Option<f32> {
+        let deck = self.decks.get(launch.deck)?;
+        let catapult = deck.config.catapult;
+        let travelled = catapult.start[1] - deck.to_deck(tow_bar).z;
+        let remaining = catapult.length - travelled;
+        (remaining > 0.0 && self.on_ground).then_some(remaining)
+    }
+
+    pub(crate) fn catapult_force(&mut self, t: &Transform) {
+        let Some(launch) = self.launch else {
+            return;
+        };
+        let front_wheel = self.plane_config.structure.front_wheel;
+        let tow_bar = local_to_global(front_wheel, t.rotation) + t.translation;
+        let Some(remaining) = self.catapult_remaining(launch, tow_bar) else {
+            return;
+        };
+        let deck = &self.decks[launch.deck];
+
+        // Whatever acceleration still reaches the end speed by the end of the stroke
+        let forward = deck.forward();
+        let speed = (self.velocity - deck.point_velocity(tow_bar)).dot(forward);
+        let accel = limit(
+            (deck.config.catapult.end_speed.powi(2) - speed * speed.abs()) / (2.0 * remaining),
+            0.0,
+            CATAPULT_MAX_ACCEL,
+        );
+
+        let force = global_to_local(forward * self.current_mass * accel, t.rotation);
+        self.add_local_force(force, front_wheel);
+    }
+
+    fn hook_up_catapult(&self, tow_bar: Vec3) -> Option<Launch> {
+        self.decks.iter().enumerate().find_map(|(index, deck)| {
+            let local = deck.to_deck(tow_bar);
+            let [x, z] = deck.config.catapult.start;
+            let relative = self.velocity - deck.point_velocity(tow_bar);
+            (vec2(local.x - x, local.z - z).length() < CATAPULT_HOOKUP_DISTANCE
+                && relative.length() < CATAPULT_HOOKUP_SPEED)
+                .then_some(Launch { deck: index })
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use crate::plane::flight_model::FlightModel;
+
+/// Damageable parts of the airframe, each backed by one of the `*_integrity` fields of
+/// `FlightModel`. Integrity runs from 1.0 (intact) to 0.0 (lost).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Part {
+    LeftWing,
+    RightWing,
+    Tail,
+    LeftEngine,
+    RightEngine,
+}
+
+impl Part {
+    pub const ALL: [Part; 5] = [
+        Part::LeftWing,
+        Part::RightWing,
+        Part::Tail,
+        Part::LeftEngine,
+        Part::RightEngine,
+    ];
+}
+
+impl FlightModel {
+    pub fn integrity(&self, part: Part) -> f32 {
+        match part {
+            Part::LeftWing => self.left_wing_integrity,
+            Part::RightWing => self.right_wing_integrity,
+            Part::Tail => self.tail_integrity,
+            Part::LeftEngine => self.left_engine_integrity,
+            Part::RightEngine => self.right_engine_integrity,
+        }
+    }
+
+    fn integrity_mut(&mut self, part: Part) -> &mut f32 {
+        match part {
+            Part::LeftWing => &mut self.left_wing_integrity,
+            Part::RightWing => &mut self.right_wing_integrity,
+            Part::Tail => &mut self.tail_integrity,
+            Part::LeftEngine => &mut self.left_engine_integrity,
+            Part::RightEngine => &mut self.right_engine_integrity,
+        }
+    }
+
+    /// Removes `amount` of integrity from `part`. Ignored while `invincible` is set.
+    pub fn apply_damage(&mut self, part: Part, amount: f32) {
+        if self.invincible {
+            return;
+        }
+
+        let integrity = self.integrity_mut(part);
+        *integrity = (*integrity - amount.max(0.0)).clamp(0.0, 1.0);
+        self.update_total_damage();
+    }
+
+    fn update_total_damage(&mut self) {
+        let lost: f32 = Part::ALL.iter().map(|&p| 1.0 - self.integrity(p)).sum();
+        self.total_damage = lost / Part::ALL.len() as f32;
+    }
+}
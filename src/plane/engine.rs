@@ -0,0 +1,183 @@
+use crate::plane::atmosphere::{SEA_LEVEL_PRESSURE, SEA_LEVEL_TEMPERATURE};
+use crate::plane::plane_config::Engine;
+
+const LIGHT_OFF_RPM: f32 = 0.25; // Fraction of max RPM where a start ignites
+const WINDMILL_RPM_PER_MACH: f32 = 0.3;
+const IDLE_EGT: f32 = 420.0; // deg C above ambient
+const MAX_EGT: f32 = 650.0; // deg C above ambient
+const EGT_TIME_CONSTANT: f32 = 3.0; // s
+
+/// Inlet conditions that decide whether an engine can burn or be started
+#[derive(Debug, Copy, Clone)]
+pub struct EngineConditions {
+    pub can_run: bool,
+    pub can_start: bool,
+    pub mach: f32,
+    pub ambient: f32, // deg C
+}
+
+/// Dry thrust relative to the sea level `max_thrust` table. That table already carries the
+/// Mach and ram effects, so this is Mattingly's low bypass turbofan lapse with the ram terms
+/// taken out: thrust falls with the static pressure and on days hotter than standard.
+pub fn thrust_lapse(pressure: f32, temperature: f32) -> f32 {
+    let delta = pressure / SEA_LEVEL_PRESSURE;
+    let theta = temperature / SEA_LEVEL_TEMPERATURE;
+    (delta * (1.0 - 0.3 * (theta - 1.0).max(0.0))).max(0.0)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EngineState {
+    Off,
+    Starting,
+    Idle,
+    Running,
+    Flameout,
+}
+
+/// Spool and start sequence of a single engine. RPM is a fraction of maximum.
+#[derive(Debug, Copy, Clone)]
+pub struct EngineSim {
+    pub state: EngineState,
+    pub rpm: f32,
+    pub egt: f32, // deg C
+    pub start_timer: f32,
+    idle_rpm: f32,
+    start_time: f32,
+    spool_up_time: f32,
+    spool_down_time: f32,
+    switch_was_on: bool,
+}
+
+impl Default for EngineSim {
+    fn default() -> Self {
+        Self {
+            state: EngineState::Off,
+            rpm: 0.0,
+            egt: 15.0,
+            start_timer: 0.0,
+            idle_rpm: 0.5,
+            start_time: 60.0,
+            spool_up_time: 4.0,
+            spool_down_time: 2.0,
+            switch_was_on: false,
+        }
+    }
+}
+
+impl EngineSim {
+    pub fn new(config: &Engine) -> Self {
+        Self {
+            idle_rpm: config.idle_rpm / 100.0,
+            start_time: config.engine_start_time,
+            spool_up_time: config.spool_up_time,
+            spool_down_time: config.spool_down_time,
+            ..Default::default()
+        }
+    }
+
+    /// Puts the engine straight into the running state at `rpm`.
+    pub fn start_hot(&mut self, rpm: f32, ambient: f32) {
+        self.rpm = rpm.max(self.idle_rpm);
+        self.state = if self.rpm > self.idle_rpm + 0.01 {
+            EngineState::Running
+        } else {
+            EngineState::Idle
+        };
+        self.egt = ambient + self.lit_egt();
+        self.switch_was_on = true;
+    }
+
+    /// True while the engine is burning fuel
+    pub fn is_lit(&self) -> bool {
+        match self.state {
+            EngineState::Idle | EngineState::Running => true,
+            EngineState::Starting => self.rpm >= LIGHT_OFF_RPM,
+            EngineState::Off | EngineState::Flameout => false,
+        }
+    }
+
+    /// Advances the state machine. `target_rpm` comes from the throttle.
+    pub fn update(
+        &mut self,
+        dt: f32,
+        switch: bool,
+        target_rpm: f32,
+        conditions: &EngineConditions,
+    ) {
+        let switched_on = switch && !self.switch_was_on;
+        self.switch_was_on = switch;
+
+        let can_run = conditions.can_run;
+        let ambient = conditions.ambient;
+        let windmill_rpm = (conditions.mach * WINDMILL_RPM_PER_MACH).min(self.idle_rpm * 0.8);
+
+        match self.state {
+            EngineState::Off => {
+                self.spool(windmill_rpm, dt);
+                if switched_on && conditions.can_start {
+                    self.state = EngineState::Starting;
+                    self.start_timer = 0.0;
+                }
+            }
+            EngineState::Starting => {
+                if !switch {
+                    self.state = EngineState::Off;
+                } else if !can_run {
+                    self.state = EngineState::Flameout;
+                } else {
+                    // Starter drives the spool linearly up to idle
+                    self.start_timer += dt;
+                    let progress = (self.start_timer / self.start_time).min(1.0);
+                    self.rpm = self.rpm.max(self.idle_rpm * progress);
+
+                    if progress >= 1.0 {
+                        self.state = EngineState::Idle;
+                    }
+                }
+            }
+            EngineState::Idle | EngineState::Running => {
+                if !switch {
+                    self.state = EngineState::Off;
+                } else if !can_run {
+                    self.state = EngineState::Flameout;
+                } else {
+                    self.spool(target_rpm.max(self.idle_rpm), dt);
+                    self.state = if self.rpm > self.idle_rpm + 0.01 {
+                        EngineState::Running
+                    } else {
+                        EngineState::Idle
+                    };
+                }
+            }
+            EngineState::Flameout => {
+                // Cycling the switch is required for a relight
+                self.spool(windmill_rpm, dt);
+                if !switch {
+                    self.state = EngineState::Off;
+                }
+            }
+        }
+
+        let target_egt = ambient
+            + match self.state {
+                EngineState::Idle | EngineState::Running => self.lit_egt(),
+                EngineState::Starting if self.is_lit() => IDLE_EGT * self.rpm / self.idle_rpm,
+                _ => 0.0,
+            };
+        self.egt += (target_egt - self.egt) * (1.0 - (-dt / EGT_TIME_CONSTANT).exp());
+    }
+
+    fn spool(&mut self, target: f32, dt: f32) {
+        let tau = if target > self.rpm {
+            self.spool_up_time
+        } else {
+            self.spool_down_time
+        };
+        self.rpm += (target - self.rpm) * (1.0 - (-dt / tau).exp());
+    }
+
+    fn lit_egt(&self) -> f32 {
+        let t = ((self.rpm - self.idle_rpm) / (1.0 - self.idle_rpm)).clamp(0.0, 1.0);
+        IDLE_EGT + (MAX_EGT - IDLE_EGT) * t
+    }
+}
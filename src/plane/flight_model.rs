@@ -1,4 +1,16 @@
+use crate::plane::atmosphere::{
+    Atmosphere, SEA_LEVEL_DENSITY, SEA_LEVEL_PRESSURE, SEA_LEVEL_TEMPERATURE,
+};
+use crate::plane::carrier_ops::{Arrest, Launch};
+use crate::plane::engine::EngineSim;
+use crate::plane::fuel::FuelSystem;
+use crate::plane::gear::GearLeg;
+use crate::plane::integrator::{Integrator, inertia_tensor};
 use crate::plane::plane_config::PlaneConfig;
+use crate::plane::wind::{Turbulence, WindField};
+use crate::world::carrier::Deck;
+use crate::world::geo::GeoReference;
+use crate::world::terrain::TerrainHeight;
 use bevy::prelude::*;
 
 #[derive(Debug)]
@@ -8,7 +20,13 @@ pub struct FlightModel {
     pub common_moment: Vec3,
     pub center_of_mass: Vec3,
     pub moment_of_inertia: [f32; 4],
+    pub inertia: Mat3,
+    pub inverse_inertia: Mat3,
     pub wind: Vec3,
+    pub wind_field: WindField,
+    pub turbulence: Turbulence,
+    pub terrain: TerrainHeight,
+    pub geo: GeoReference,
     pub velocity: Vec3,
     pub angular_velocity: Vec3,
     pub airspeed: Vec3,
@@ -51,12 +69,14 @@ pub struct FlightModel {
     pub yaw_trim: f32,
     pub rudder_command: f32,
 
+    pub left_engine: EngineSim,
     pub left_engine_switch: bool,
     pub left_throttle_input: f32,
     pub left_throttle_output: f32,
     pub left_engine_power_readout: f32,
     pub left_thrust_force: f32,
 
+    pub right_engine: EngineSim,
     pub right_engine_switch: bool,
     pub right_throttle_input: f32,
     pub right_throttle_output: f32,
@@ -71,15 +91,27 @@ pub struct FlightModel {
 
     pub gear_switch: bool,
     pub gear_pos: f32,
+    pub gear_legs: [GearLeg; 3],
     pub wheel_brake: f32,
-    pub carrier_pos: i32,
+    pub anti_skid: bool,
+    /// Carrier decks to land on, refreshed every step
+    pub decks: Vec<Deck>,
+    pub hook_down: bool,
+    /// Deck and deck position of the hook tip last step, to find the wire it crossed
+    pub hook_last: Option<(usize, Vec3)>,
+    pub arrest: Option<Arrest>,
+    pub catapult_request: bool,
+    pub launch: Option<Launch>,
 
+    pub fuel: FuelSystem,
     pub internal_fuel: f32,
     pub external_fuel: f32,
     pub total_fuel: f32,
     pub fuel_consumption_since_last_time: f32,
 
+    pub atmosphere: Atmosphere,
     pub atmosphere_density: f32,
+    pub atmosphere_pressure: f32,
     pub altitude_asl: f32,
     pub altitude_agl: f32,
     pub v_scalar: f32,
@@ -91,18 +123,25 @@ pub struct FlightModel {
     pub alpha: f32,
     pub aos: f32,
     pub beta: f32,
+    /// Flow separation over the wings, 0 fully attached to 1 fully stalled
+    pub stall: f32,
+    /// Share of the control surface authority left by compressibility
+    pub control_effectiveness: f32,
     pub g: f32,
     pub atmosphere_temperature: f32,
 
     pub on_ground: bool,
+    pub crashed: bool,
+    pub impact_speed: f32,
     pub pitch: f32,
     pub pitch_rate: f32,
     pub roll: f32,
     pub roll_rate: f32,
     pub heading: f32,
     pub yaw_rate: f32,
+    pub true_heading: f32,
+    pub magnetic_heading: f32,
 
-    pub element_integrity: [i32; 111],
     pub left_wing_integrity: f32,
     pub right_wing_integrity: f32,
     pub tail_integrity: f32,
@@ -117,6 +156,7 @@ pub struct FlightModel {
     pub shake_amplitude: f32,
     pub fm_clock: f32,
     pub sim_initialised: bool,
+    pub integrator: Integrator,
 
     pub plane_config: PlaneConfig,
 }
@@ -128,7 +168,9 @@ impl FlightModel {
         let length = data.basic.length;
         let height = data.basic.height;
         let idle_rpm = data.engine.idle_rpm / 100.0;
-        let current_mass = data.basic.gross_mass;
+        let fuel = FuelSystem::new(&data.fuel);
+        let current_mass = data.basic.empty_mass + fuel.total();
+        let inertia = inertia_tensor(data.basic.moment_of_inertia);
 
         let cm_vec = Vec3 {
             x: cm[0],
@@ -139,12 +181,18 @@ impl FlightModel {
         Self {
             center_of_mass: cm_vec,
             moment_of_inertia: data.basic.moment_of_inertia,
+            inertia,
+            inverse_inertia: inertia.inverse(),
             wingspan: wingspan,
             length: length,
             height: height,
             idle_rpm: idle_rpm,
             s: data.basic.wing_area,
             current_mass: current_mass,
+            internal_fuel: fuel.internal(),
+            external_fuel: fuel.external(),
+            total_fuel: fuel.total(),
+            fuel,
             draw_vecs: Vec::new(),
             left_wing_pos: data.structure.left_wing_pos,
             right_wing_pos: data.structure.right_wing_pos,
@@ -155,6 +203,9 @@ impl FlightModel {
             rudder_pos: data.structure.rudder_pos,
             left_engine_pos: data.structure.left_engine_pos,
             right_engine_pos: data.structure.right_engine_pos,
+            integrator: data.simulation.integrator,
+            left_engine: EngineSim::new(&data.engine),
+            right_engine: EngineSim::new(&data.engine),
             plane_config: data,
             ..Default::default()
         }
@@ -168,7 +219,13 @@ impl Default for FlightModel {
             common_moment: Vec3::default(),
             center_of_mass: Vec3::default(),
             moment_of_inertia: [0.0; 4],
+            inertia: Mat3::IDENTITY,
+            inverse_inertia: Mat3::IDENTITY,
             wind: Vec3::default(),
+            wind_field: WindField::default(),
+            turbulence: Turbulence::default(),
+            terrain: TerrainHeight::default(),
+            geo: GeoReference::default(),
             velocity: Vec3::default(),
             angular_velocity: Vec3::default(),
             current_mass: 0.0,
@@ -203,11 +260,13 @@ impl Default for FlightModel {
             yaw_analog: true,
             yaw_trim: 0.0,
             rudder_command: 0.0,
+            left_engine: EngineSim::default(),
             left_engine_switch: false,
             left_throttle_input: 0.0,
             left_throttle_output: 0.0,
             left_engine_power_readout: 0.0,
             left_thrust_force: 0.0,
+            right_engine: EngineSim::default(),
             right_engine_switch: false,
             right_throttle_input: 0.0,
             right_throttle_output: 0.0,
@@ -220,34 +279,49 @@ impl Default for FlightModel {
             slats_pos: 0.0,
             gear_switch: false,
             gear_pos: 0.0,
+            gear_legs: [GearLeg::default(); 3],
             wheel_brake: 0.0,
-            carrier_pos: 0,
+            anti_skid: true,
+            decks: Vec::new(),
+            hook_down: false,
+            hook_last: None,
+            arrest: None,
+            catapult_request: false,
+            launch: None,
             velocity_local: Vec3::default(),
+            fuel: FuelSystem::default(),
             internal_fuel: 0.0,
             external_fuel: 0.0,
             total_fuel: 0.0,
             fuel_consumption_since_last_time: 0.0,
-            atmosphere_density: 1.0100, // change this to a table
+            atmosphere: Atmosphere::default(),
+            atmosphere_density: SEA_LEVEL_DENSITY,
+            atmosphere_pressure: SEA_LEVEL_PRESSURE,
             altitude_asl: 0.0,
             altitude_agl: 0.0,
             v_scalar: 0.0,
-            speed_of_sound: 320.0,
+            speed_of_sound: 340.3,
             mach: 0.0,
             engine_alt_effect: 1.0,
             aoa: 0.0,
             alpha: 0.0,
             aos: 0.0,
             beta: 0.0,
+            stall: 0.0,
+            control_effectiveness: 1.0,
             g: 0.0,
-            atmosphere_temperature: 273.0,
+            atmosphere_temperature: SEA_LEVEL_TEMPERATURE,
             on_ground: false,
+            crashed: false,
+            impact_speed: 0.0,
             pitch: 0.0,
             pitch_rate: 0.0,
             roll: 0.0,
             roll_rate: 0.0,
             heading: 0.0,
             yaw_rate: 0.0,
-            element_integrity: [0; 111],
+            true_heading: 0.0,
+            magnetic_heading: 0.0,
             left_wing_integrity: 1.0,
             right_wing_integrity: 1.0,
             tail_integrity: 1.0,
@@ -260,7 +334,8 @@ impl Default for FlightModel {
             shake_amplitude: 0.0,
             fm_clock: 0.0,
             sim_initialised: false,
-            plane_config: PlaneConfig::new("su-25t"),
+            integrator: Integrator::default(),
+            plane_config: PlaneConfig::default(),
         }
     }
 }
@@ -1,24 +1,55 @@
+use crate::plane::atmosphere::{SEA_LEVEL_DENSITY, ram_pressure_ratio};
+use crate::plane::engine::{EngineConditions, EngineSim, EngineState, thrust_lapse};
 use crate::plane::flight_model::FlightModel;
 use crate::util::{actuator, limit, rad, rescale, table_lerp};
+use crate::world::frames;
 use bevy::prelude::*;
 
 const GRAV: Vec3 = vec3(0.0, -9.81, 0.0);
-pub const ALTITUDE_M: [f32; 16] = [
-    0.0, 500.0, 1000.0, 1500.0, 2000.0, 2500.0, 3000.0, 3500.0, 4000.0, 4500.0, 5000.0, 6000.0,
-    7000.0, 8000.0, 9000.0, 10000.0,
-];
-pub const AIR_DENSITY_KG_PER_M3: [f32; 16] = [
-    1.225, 1.167, 1.112, 1.058, 1.007, 0.957, 0.909, 0.863, 0.819, 0.777, 0.736, 0.660, 0.590,
-    0.525, 0.467, 0.414,
-];
+
+// Actuator and input rates, in units per second
+const PITCH_INPUT_RATE: f32 = 0.21;
+const ROLL_INPUT_RATE: f32 = 0.24;
+const YAW_INPUT_RATE: f32 = 0.21;
+const ELEVATOR_RATE: f32 = 0.75;
+const AILERON_RATE: f32 = 1.2;
+const RUDDER_RATE: f32 = 0.72;
+const AIRBRAKE_RETRACT_RATE: f32 = 0.18;
+const AIRBRAKE_EXTEND_RATE: f32 = 0.24;
+const FLAPS_RATE: f32 = 0.12;
+const SLATS_RATE: f32 = 0.18;
+
+// Fraction of the stick input kept after one second of centring
+const PITCH_INPUT_DECAY: f32 = 0.298;
+const ROLL_INPUT_DECAY: f32 = 0.0018;
+const YAW_INPUT_DECAY: f32 = 0.0018;
+
+// Past the critical angle the lift curve blends into a flat plate over this many degrees
+const STALL_TRANSITION: f32 = 6.0;
+// Normal force coefficient of a fully stalled wing broadside to the flow
+const FLAT_PLATE_NORMAL: f32 = 1.8;
+// Degrees of critical angle the trailing wing loses per degree of sideslip
+const SIDESLIP_STALL_SHIFT: f32 = 0.3;
+
+// Control authority left at mach_max, shocks on the surfaces separate the flow over the hinges
+const CONTROL_EFFECTIVENESS_AT_MACH_MAX: f32 = 0.6;
+
+/// Force coefficients of one wing half, across and along the body
+#[derive(Debug, Copy, Clone)]
+pub struct WingCoefficients {
+    pub lift: f32,
+    pub drag: f32,
+    /// 0 attached to 1 fully stalled
+    pub stall: f32,
+}
 
 #[inline(always)]
-fn local_to_global(local_vec: Vec3, world_rot: Quat) -> Vec3 {
+pub(crate) fn local_to_global(local_vec: Vec3, world_rot: Quat) -> Vec3 {
     world_rot.normalize() * local_vec
 }
 
 #[inline(always)]
-fn global_to_local(global_vec: Vec3, world_rot: Quat) -> Vec3 {
+pub(crate) fn global_to_local(global_vec: Vec3, world_rot: Quat) -> Vec3 {
     world_rot.normalize().inverse() * global_vec
 }
 
@@ -36,7 +67,7 @@ impl FlightModel {
         self.add_local_force(force, force_pos);
     }
 
-    fn add_local_force(&mut self, force: Vec3, force_pos: Vec3) {
+    pub(crate) fn add_local_force(&mut self, force: Vec3, force_pos: Vec3) {
         self.common_force += force;
 
         let delta_pos = force_pos - self.center_of_mass;
@@ -45,80 +76,221 @@ impl FlightModel {
         self.common_moment += delta_moment;
     }
 
-    fn sim_engine(&mut self, mach: &f32, dt: &f32, _t: &Transform) {
+    fn update_engines(&mut self, dt: f32) {
         let et = &self.plane_config.engine.tables;
-        let max_dry_thrust = table_lerp(&et.mach, &et.max_thrust, *mach);
 
         self.left_throttle_input = limit(self.left_throttle_input, 0.0, 1.0);
         self.right_throttle_input = limit(self.right_throttle_input, 0.0, 1.0);
 
-        // Left engine
-        self.left_throttle_output = limit(
-            table_lerp(
-                &et.throttle_input,
-                &et.engine_power,
-                self.left_throttle_input,
-            ),
-            0.1,
-            1.0,
+        let left_target_rpm = table_lerp(
+            &et.throttle_input,
+            &et.engine_power_readout,
+            self.left_throttle_input,
         );
-        self.left_engine_power_readout = limit(
-            table_lerp(
-                &et.throttle_input,
-                &et.engine_power_readout,
-                self.left_throttle_input,
-            ),
-            0.0,
-            1.0,
+        let right_target_rpm = table_lerp(
+            &et.throttle_input,
+            &et.engine_power_readout,
+            self.right_throttle_input,
         );
 
-        // Right engine
-        self.right_throttle_output = limit(
-            table_lerp(
-                &et.throttle_input,
-                &et.engine_power,
-                self.right_throttle_input,
-            ),
-            0.1,
-            1.0,
+        // Combustion needs fuel and enough inlet total pressure
+        let has_fuel = self.total_fuel > 0.0 || self.infinite_fuel;
+        let inlet_pressure = self.atmosphere_pressure * ram_pressure_ratio(self.mach);
+        let conditions = EngineConditions {
+            can_run: has_fuel && inlet_pressure >= self.plane_config.engine.flameout_pressure,
+            can_start: has_fuel && inlet_pressure >= self.plane_config.engine.relight_pressure,
+            mach: self.mach,
+            ambient: self.atmosphere_temperature - 273.15,
+        };
+
+        // A destroyed engine cannot keep burning
+        let left_conditions = EngineConditions {
+            can_run: conditions.can_run && self.left_engine_integrity > 0.0,
+            can_start: conditions.can_start && self.left_engine_integrity > 0.0,
+            ..conditions
+        };
+        let right_conditions = EngineConditions {
+            can_run: conditions.can_run && self.right_engine_integrity > 0.0,
+            can_start: conditions.can_start && self.right_engine_integrity > 0.0,
+            ..conditions
+        };
+
+        self.left_engine.update(
+            dt,
+            self.left_engine_switch,
+            left_target_rpm,
+            &left_conditions,
         );
-        self.right_engine_power_readout = limit(
-            table_lerp(
-                &et.throttle_input,
-                &et.engine_power_readout,
-                self.right_throttle_input,
+        self.right_engine.update(
+            dt,
+            self.right_engine_switch,
+            right_target_rpm,
+            &right_conditions,
+        );
+
+        self.left_throttle_output = self.engine_power(&self.left_engine);
+        self.left_engine_power_readout = self.left_engine.rpm;
+        self.right_throttle_output = self.engine_power(&self.right_engine);
+        self.right_engine_power_readout = self.right_engine.rpm;
+    }
+
+    /// Fraction of maximum thrust delivered at the engine's current RPM
+    fn engine_power(&self, engine: &EngineSim) -> f32 {
+        let et = &self.plane_config.engine.tables;
+        let power = table_lerp(&et.engine_power_readout, &et.engine_power, engine.rpm);
+
+        match engine.state {
+            EngineState::Idle | EngineState::Running => limit(power, 0.1, 1.0),
+            EngineState::Starting if engine.is_lit() => limit(power, 0.0, 1.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Burns fuel for the running engines and updates the aircraft mass.
+    fn update_fuel(&mut self, dt: f32) {
+        let engine = &self.plane_config.engine;
+        let density_ratio = self.atmosphere_density / SEA_LEVEL_DENSITY;
+
+        let flow = engine.fuel_consumption
+            * (self.left_throttle_output + self.right_throttle_output)
+            * density_ratio;
+
+        self.fuel_consumption_since_last_time = if self.infinite_fuel {
+            0.0
+        } else {
+            self.fuel.burn(flow * dt)
+        };
+
+        self.internal_fuel = self.fuel.internal();
+        self.external_fuel = self.fuel.external();
+        self.total_fuel = self.fuel.total();
+        self.current_mass = self.plane_config.basic.empty_mass + self.total_fuel;
+    }
+
+    fn sim_engine(&mut self, mach: &f32, _t: &Transform) {
+        let et = &self.plane_config.engine.tables;
+        let max_dry_thrust = match &et.max_thrust_altitude {
+            Some(thrust) => {
+                self.engine_alt_effect = 1.0;
+                thrust.lookup(&[self.altitude_asl, *mach])
+            }
+            None => {
+                self.engine_alt_effect =
+                    thrust_lapse(self.atmosphere_pressure, self.atmosphere_temperature);
+                table_lerp(&et.mach, &et.max_thrust, *mach)
+            }
+        };
+
+        self.left_thrust_force = self.left_throttle_output
+            * max_dry_thrust
+            * self.engine_alt_effect
+            * self.left_engine_integrity
+            * 0.5;
+        self.right_thrust_force = self.right_throttle_output
+            * max_dry_thrust
+            * self.engine_alt_effect
+            * self.right_engine_integrity
+            * 0.5;
+
+        self.add_local_force(
+            vec3(0.0, 0.0, -self.left_thrust_force),
+            self.left_engine_pos,
+        );
+        self.add_local_force(
+            vec3(0.0, 0.0, -self.right_thrust_force),
+            self.right_engine_pos,
+        );
+    }
+
+    /// Lift and drag of the wing half whose force acts at `wing_pos`. Below the stall
+    /// both halves follow the linear lift curve at the fuselage alpha. Past it each
+    /// half sees its own alpha, raised on the descending wing by the rotation, and
+    /// blends into the normal force of a flat plate, which acts across the wing and
+    /// so also brings the post-stall drag. The lift lost by the descending half drives
+    /// autorotation.
+    fn wing_coefficients(
+        &self,
+        wing_pos: Vec3,
+        cy_alpha: f32,
+        cy_max: f32,
+        lift: f32,
+        drag: f32,
+    ) -> WingCoefficients {
+        let flow = self.airspeed + self.angular_velocity.cross(wing_pos - self.center_of_mass);
+        let alpha = if flow.length() > 10.0 {
+            -flow.y.atan2(-flow.z).to_degrees()
+        } else {
+            self.alpha
+        };
+
+        // The trailing wing in a sideslip is the first to let go
+        let trailing = if wing_pos.x < 0.0 {
+            self.beta.max(0.0)
+        } else {
+            (-self.beta).max(0.0)
+        };
+        let alpha_critical = cy_max / cy_alpha.max(1e-4) - trailing * SIDESLIP_STALL_SHIFT;
+
+        let x = limit((alpha.abs() - alpha_critical) / STALL_TRANSITION, 0.0, 1.0);
+        let stall = x * x * (3.0 - 2.0 * x);
+
+        let (attached, separated) = match &self.plane_config.aerodynamics.tables.Cy {
+            Some(cy) => (
+                cy.lookup(&[self.alpha, self.mach]),
+                cy.lookup(&[alpha, self.mach]),
             ),
+            None => (
+                limit(cy_alpha * self.alpha, -cy_max, cy_max),
+                FLAT_PLATE_NORMAL * rad(alpha).sin(),
+            ),
+        };
+
+        WingCoefficients {
+            lift: lift + attached + (separated - attached) * stall,
+            drag,
+            stall,
+        }
+    }
+
+    /// Drag coefficient added by the shock waves once the flow over the wing goes supersonic.
+    /// Lock's empirical law, it grows with the fourth power of the Mach past critical and
+    /// reaches `wave_drag` at `mach_max`.
+    fn wave_drag(&self, mach: f32) -> f32 {
+        let aero = &self.plane_config.aerodynamics;
+        let past_critical = (mach.min(1.0) - aero.mach_critical).max(0.0);
+        let at_mach_max = (self.plane_config.basic.mach_max - aero.mach_critical).max(1e-3);
+        aero.wave_drag * (past_critical / at_mach_max).powi(4)
+    }
+
+    /// Aft shift of the wing lift, in meters. The shocks move the center of pressure back
+    /// and pitch the nose down as the aircraft approaches Mach 1.
+    fn mach_tuck(&self, mach: f32) -> f32 {
+        let aero = &self.plane_config.aerodynamics;
+        let x = limit(
+            (mach - aero.mach_critical) / (1.0 - aero.mach_critical).max(1e-3),
             0.0,
             1.0,
         );
+        aero.mach_tuck * x * x * (3.0 - 2.0 * x)
+    }
 
-        let left_thrust_force =
-            self.left_throttle_output * max_dry_thrust * self.engine_alt_effect * 0.5;
-        let right_thrust_force =
-            self.right_throttle_output * max_dry_thrust * self.engine_alt_effect * 0.5;
-
-        // Engine shutdown
-        if self.internal_fuel <= 0.0 || self.altitude_asl > 20_000.0 {
-            self.left_thrust_force = 0.0;
-            self.right_thrust_force = 0.0;
-            self.left_engine_switch = false;
-            self.right_engine_switch = false;
-
-            self.left_engine_power_readout =
-                actuator(self.left_engine_power_readout, 0.0, -dt / 10.0, dt / 10.0);
-            self.right_engine_power_readout =
-                actuator(self.right_engine_power_readout, 0.0, -dt / 10.0, dt / 10.0);
-        }
-
-        self.add_local_force(vec3(0.0, 0.0, -left_thrust_force), self.left_engine_pos);
-        self.add_local_force(vec3(0.0, 0.0, -right_thrust_force), self.right_engine_pos);
+    /// Share of the control surface authority left past the critical Mach
+    fn control_effectiveness(&self, mach: f32) -> f32 {
+        let mach_critical = self.plane_config.aerodynamics.mach_critical;
+        let mach_max = self.plane_config.basic.mach_max;
+        let fade = limit(
+            (mach - mach_critical) / (mach_max - mach_critical).max(1e-3),
+            0.0,
+            1.5,
+        );
+        1.0 - fade * (1.0 - CONTROL_EFFECTIVENESS_AT_MACH_MAX)
     }
 
     pub fn update_wings(
         &mut self,
         alpha_max: f32,
-        lift: f32,
-        drag: f32,
+        left: WingCoefficients,
+        right: WingCoefficients,
         q: f32,
         s: f32,
         aos: f32,
@@ -147,16 +319,20 @@ impl FlightModel {
             self.right_wing_pos.z = self.center_of_mass.z + 0.7;
         }
 
+        let tuck = self.mach_tuck(self.mach);
+        self.left_wing_pos.z += tuck;
+        self.right_wing_pos.z += tuck;
+
         let left_wing_forces = vec3(
             0.0,
-            lift * ((-aos / 2.0).sin() / 2.0 + 1.0) * q * (s / 2.0),
-            drag * ((-aos / 2.0).sin() + 1.0) * q * (s / 2.0),
+            left.lift * ((-aos / 2.0).sin() / 2.0 + 1.0) * q * (s / 2.0),
+            left.drag * ((-aos / 2.0).sin() + 1.0) * q * (s / 2.0),
         );
 
         let right_wing_forces = vec3(
             0.0,
-            lift * ((-aos / 2.0).sin() / 2.0 + 1.0) * q * (s / 2.0),
-            drag * ((-aos / 2.0).sin() + 1.0) * q * (s / 2.0),
+            right.lift * ((-aos / 2.0).sin() / 2.0 + 1.0) * q * (s / 2.0),
+            right.drag * ((-aos / 2.0).sin() + 1.0) * q * (s / 2.0),
         );
 
         let tail_force = vec3(
@@ -168,26 +344,32 @@ impl FlightModel {
         // self.add_local_force_draw(tail_force, self.tail_pos, t);
         // self.add_local_force_draw(right_wing_forces, self.right_wing_pos, t);
         // self.add_local_force_draw(left_wing_forces, self.left_wing_pos, t);
-        self.add_local_force(left_wing_forces, self.left_wing_pos);
-        self.add_local_force(right_wing_forces, self.right_wing_pos);
-        self.add_local_force(tail_force, self.tail_pos);
+        self.add_local_force(
+            left_wing_forces * self.left_wing_integrity,
+            self.left_wing_pos,
+        );
+        self.add_local_force(
+            right_wing_forces * self.right_wing_integrity,
+            self.right_wing_pos,
+        );
+        self.add_local_force(tail_force * self.tail_integrity, self.tail_pos);
     }
 
-    pub fn update_elevator(&mut self, aoa: f32, q: f32, mach: &f32, _t: &Transform) {
+    fn update_pitch_controls(&mut self, dt: f32) {
         if self.pitch_analog {
             self.pitch_input = limit(self.pitch_input, -1.0, 1.0);
         } else {
             if self.pitch_discrete == 1 {
-                self.pitch_input = (self.pitch_input + 0.0035).min(1.0);
+                self.pitch_input = (self.pitch_input + PITCH_INPUT_RATE * dt).min(1.0);
             }
-            if self.pitch_discrete == 0 && self.pitch_input > 0.5 && self.pitch_input > 0.7 {
-                self.pitch_input *= 0.98;
+            if self.pitch_discrete == 0 && self.pitch_input > 0.7 {
+                self.pitch_input *= PITCH_INPUT_DECAY.powf(dt);
             }
             if self.pitch_discrete == -1 {
-                self.pitch_input = (self.pitch_input - 0.0035).max(-1.0);
+                self.pitch_input = (self.pitch_input - PITCH_INPUT_RATE * dt).max(-1.0);
             }
-            if self.pitch_discrete == 0 && self.pitch_input < -0.5 && self.pitch_input < -0.5 {
-                self.pitch_input *= 0.98;
+            if self.pitch_discrete == 0 && self.pitch_input < -0.5 {
+                self.pitch_input *= PITCH_INPUT_DECAY.powf(dt);
             }
         }
 
@@ -196,47 +378,45 @@ impl FlightModel {
             actuator(
                 self.elevator_command,
                 self.pitch_input + self.pitch_trim,
-                -0.0125,
-                0.0125,
+                -ELEVATOR_RATE * dt,
+                ELEVATOR_RATE * dt,
             ),
             -1.0,
             1.0,
         );
+    }
 
-        let elevator_deflection = -(rescale(self.elevator_command + 0.15, rad(-25.0), rad(35.0)))
-            * 14.0
-            * (aoa / 2.0).cos();
-        let pitch_stability = (aoa + (aoa / 2.0).sin() / 2.0) + (self.pitch_rate * 2.0);
-
-        let f = vec3(
-            0.0,
-            ((elevator_deflection
-                * limit(
-                    1.0 - ((mach + self.plane_config.basic.mach_max * 0.4) / 3.0).sqrt(),
-                    0.001,
-                    1.0,
-                ))
-                + (pitch_stability * (mach / 2.0 + 1.0)))
-                * q
-                / 2.0,
-            0.0,
-        );
-        // self.add_local_force_draw(f, self.elevator_pos, t);
-        self.add_local_force(f, self.elevator_pos);
+    pub fn update_elevator(&mut self, aoa: f32, q: f32) {
+        let d = &self.plane_config.aerodynamics.derivatives;
+        // Trailing edge down is positive, pulling raises the trailing edge
+        let elevator = -rescale(
+            self.elevator_command,
+            rad(d.elevator_down),
+            rad(d.elevator_up),
+        ) * self.control_effectiveness;
+        let rates = self.normalized_rates();
+
+        let static_cm = match &self.plane_config.aerodynamics.tables.Cm {
+            Some(cm) => cm.lookup(&[self.alpha, self.mach]),
+            None => d.Cm0 + d.Cm_alpha * aoa,
+        };
+        let cm = static_cm + d.Cm_q * rates.y + d.Cm_de * elevator * (aoa / 2.0).cos();
+
+        self.add_moment_coefficients(vec3(0.0, cm, 0.0) * self.tail_integrity, q);
     }
 
-    pub fn update_roll(&mut self, aos: f32, aoa: f32, q: f32, _t: &Transform) {
+    fn update_roll_controls(&mut self, dt: f32) {
         if self.roll_analog {
             self.roll_input = limit(self.roll_input, -1.0, 1.0);
         } else {
             if self.roll_discrete == 1 {
-                self.roll_input = (self.roll_input + 0.004).min(1.0);
+                self.roll_input = (self.roll_input + ROLL_INPUT_RATE * dt).min(1.0);
             }
             if self.roll_discrete == -1 {
-                self.roll_input = (self.roll_input - 0.004).max(-1.0);
+                self.roll_input = (self.roll_input - ROLL_INPUT_RATE * dt).max(-1.0);
             }
             if self.roll_discrete == 0 {
-                self.roll_input *= 0.9;
+                self.roll_input *= ROLL_INPUT_DECAY.powf(dt);
             }
         }
 
@@ -245,51 +425,43 @@ impl FlightModel {
             actuator(
                 self.aileron_command,
                 self.roll_input + self.roll_trim,
-                -0.02,
-                0.02,
+                -AILERON_RATE * dt,
+                AILERON_RATE * dt,
             ),
             -1.0,
             1.0,
         );
+    }
 
-        let aileron_deflection = rescale(self.aileron_command, rad(-30.0), rad(30.0)) * 4.0;
-        let roll_stability = -self.roll_rate
-            * (((aoa + 0.5).abs() * (aos + 0.5).abs()) + 1.0)
-            * (5.0 / self.wingspan)
-            + ((self.roll.sin() / 2.0) * (aoa / 2.0).abs());
-
-        // self.add_local_force_draw(
-        //     vec3(0.0, (aileron_deflection + roll_stability) * q, 0.0),
-        //     self.left_aileron_pos,
-        //     t,
-        // );
-        // self.add_local_force_draw(
-        //     vec3(0.0, -(aileron_deflection + roll_stability) * q, 0.0),
-        //     self.right_aileron_pos,
-        //     t,
-        // );
-        self.add_local_force(
-            vec3(0.0, (aileron_deflection + roll_stability) * q, 0.0),
-            self.left_aileron_pos,
-        );
-        self.add_local_force(
-            vec3(0.0, -(aileron_deflection + roll_stability) * q, 0.0),
-            self.right_aileron_pos,
-        );
+    pub fn update_roll(&mut self, q: f32) {
+        let d = &self.plane_config.aerodynamics.derivatives;
+        let aileron = rescale(
+            self.aileron_command,
+            rad(d.aileron_travel),
+            rad(d.aileron_travel),
+        ) * self.control_effectiveness;
+        let rates = self.normalized_rates();
+
+        // Damping from attached flow, the stalled wing halves take over past the stall
+        let cl = d.Cl_p * rates.x * (1.0 - self.stall) + d.Cl_da * aileron;
+        let cn = d.Cn_da * aileron;
+        let wing_integrity = (self.left_wing_integrity + self.right_wing_integrity) / 2.0;
+
+        self.add_moment_coefficients(vec3(cl, 0.0, cn) * wing_integrity, q);
     }
 
-    pub fn update_yaw(&mut self, aos: f32, q: f32, _t: &Transform) {
+    fn update_yaw_controls(&mut self, dt: f32) {
         if self.yaw_analog {
             self.yaw_input = limit(self.yaw_input, -1.0, 1.0);
         } else {
             if self.yaw_discrete == 1 {
-                self.yaw_input = (self.yaw_input + 0.0035).min(1.0);
+                self.yaw_input = (self.yaw_input + YAW_INPUT_RATE * dt).min(1.0);
             }
             if self.yaw_discrete == -1 {
-                self.yaw_input = (self.yaw_input - 0.0035).max(-1.0);
+                self.yaw_input = (self.yaw_input - YAW_INPUT_RATE * dt).max(-1.0);
             }
             if self.yaw_discrete == 0 {
-                self.yaw_input *= 0.9;
+                self.yaw_input *= YAW_INPUT_DECAY.powf(dt);
             }
         }
 
@@ -298,50 +470,55 @@ impl FlightModel {
             actuator(
                 self.rudder_command,
                 self.yaw_input + self.yaw_trim,
-                -0.012,
-                0.012,
+                -RUDDER_RATE * dt,
+                RUDDER_RATE * dt,
             ),
             -1.0,
             1.0,
         );
-
-        let rudder_deflection = rescale(self.rudder_command, rad(-30.0), rad(30.0)) * 1.5;
-        let yaw_stability = -((aos * 2.0) + self.yaw_rate);
-
-        self.add_local_force(
-            vec3((rudder_deflection + yaw_stability) * q, 0.0, 0.0),
-            self.rudder_pos,
-        );
-        self.add_local_force(
-            vec3((rudder_deflection + yaw_stability) * q, 0.0, 0.0),
-            self.rudder_pos,
-        );
     }
 
-    pub fn update_other(&mut self, q: f32, omx_max: f32, mach: f32, aos: f32) {
-        let roll_yaw_moment = -(self.roll_rate / 2.0) * (q + 1e5 * 0.5); // Subtle yaw moment to keep stable in sharp turns
-        self.add_local_moment(vec3(0.0, roll_yaw_moment, 0.0));
-
-        let roll_rate_limiter = -self.roll_rate
-            * limit(
-                (limit(self.roll_rate.abs() / (omx_max + 0.1), 0.0001, 2.0)).powi(6)
-                    * (q + q + 1e5 * 0.3),
-                -1e7,
-                1e7,
-            );
-
-        self.add_local_moment(Vec3::new(roll_rate_limiter, 0.0, 0.0));
+    pub fn update_yaw(&mut self, aos: f32, q: f32) {
+        let d = &self.plane_config.aerodynamics.derivatives;
+        // Positive rudder turns the nose left
+        let rudder = rescale(
+            self.rudder_command,
+            rad(d.rudder_travel),
+            rad(d.rudder_travel),
+        ) * self.control_effectiveness;
+        let rates = self.normalized_rates();
+
+        // The fin sits above the roll axis, so its side force also rolls the aircraft
+        let cl = d.Cl_beta * aos + d.Cl_r * rates.z + d.Cl_dr * rudder;
+        let cn = d.Cn_beta * aos + d.Cn_p * rates.x + d.Cn_r * rates.z + d.Cn_dr * rudder;
+
+        self.add_moment_coefficients(vec3(cl, 0.0, cn) * self.tail_integrity, q);
+    }
 
-        let yaw_rate_limiter = -(self.yaw_rate + aos) * (q + 1e5 * 0.5);
-        self.add_local_moment(Vec3::new(0.0, yaw_rate_limiter, 0.0));
+    /// Roll, pitch and yaw rates made dimensionless with the span, the mean chord and
+    /// twice the airspeed
+    fn normalized_rates(&self) -> Vec3 {
+        let basic = &self.plane_config.basic;
+        let chord = basic.wing_area / basic.wingspan;
+        let speed = 2.0 * self.airspeed.length().max(1.0);
+        vec3(
+            self.roll_rate * basic.wingspan,
+            self.pitch_rate * chord,
+            self.yaw_rate * basic.wingspan,
+        ) / speed
+    }
 
-        let speed_limiter = limit(
-            (mach.abs() / self.plane_config.basic.mach_max).powi(5) * (q + 1e5 * 0.5),
-            -1e7,
-            1e7,
-        );
-        self.add_local_force(vec3(0.0, 0.0, speed_limiter), self.center_of_mass);
+    /// Adds the moment of the roll, pitch and yaw coefficients, referenced to the wing
+    /// area and to the span for roll and yaw, the mean chord for pitch
+    fn add_moment_coefficients(&mut self, coefficients: Vec3, q: f32) {
+        let basic = &self.plane_config.basic;
+        let chord = basic.wing_area / basic.wingspan;
+        let moment =
+            coefficients * vec3(basic.wingspan, chord, basic.wingspan) * q * basic.wing_area;
+        self.add_local_moment(frames::from_frd(moment));
+    }
 
+    pub fn update_other(&mut self, mach: f32) {
         self.shake_amplitude = 0.0;
 
         self.shake_amplitude += limit(
@@ -351,6 +528,8 @@ impl FlightModel {
         ) / 6.0;
 
         if !self.on_ground {
+            self.shake_amplitude += self.stall / 5.0;
+
             if self.alpha.abs() > 10.0 {
                 self.shake_amplitude += (self.alpha.abs() - 10.0) / 100.0;
             }
@@ -369,22 +548,28 @@ impl FlightModel {
         }
     }
 
+    /// Advances controls and actuators by `dt`, then builds up forces and moments.
     pub fn simulate(&mut self, dt: f32, t: &Transform) {
-        self.common_moment = Vec3::ZERO;
-        self.common_force = Vec3::ZERO;
-        self.draw_vecs = Vec::new();
+        self.update_controls(dt);
+        self.compute_forces(t);
+    }
 
-        self.gear_pos = limit(
-            actuator(self.gear_pos, self.gear_switch as u8 as f32, -0.001, 0.001),
-            0.0,
-            1.0,
-        ); // Landing gear (all 3)
+    fn update_controls(&mut self, dt: f32) {
+        self.fm_clock += dt;
+        self.turbulence.update(
+            dt,
+            self.airspeed.length(),
+            self.altitude_agl,
+            self.wind_field.turbulence_intensity,
+        );
+
+        self.update_gear(dt);
         self.airbrake_pos = limit(
             actuator(
                 self.airbrake_pos,
                 self.airbrake_switch as u8 as f32,
-                -0.003,
-                0.004,
+                -AIRBRAKE_RETRACT_RATE * dt,
+                AIRBRAKE_EXTEND_RATE * dt,
             ),
             0.0,
             1.0,
@@ -393,21 +578,38 @@ impl FlightModel {
             actuator(
                 self.flaps_pos,
                 self.flaps_switch as u8 as f32,
-                -0.002,
-                0.002,
+                -FLAPS_RATE * dt,
+                FLAPS_RATE * dt,
             ),
             0.0,
             1.0,
         ); // Flaps
         self.slats_pos = limit(
-            actuator(self.slats_pos, (self.alpha - 6.0) / 12.0, -0.003, 0.003),
+            actuator(
+                self.slats_pos,
+                (self.alpha - 6.0) / 12.0,
+                -SLATS_RATE * dt,
+                SLATS_RATE * dt,
+            ),
             0.0,
             1.0,
         ); // Slats, starts moving at 6 degrees alpha
 
-        // --- Aerodynamics ---
-        self.airspeed = self.velocity_local;
+        self.update_pitch_controls(dt);
+        self.update_roll_controls(dt);
+        self.update_yaw_controls(dt);
+        self.update_engines(dt);
+        self.update_fuel(dt);
+    }
+
+    /// Builds `common_force` and `common_moment` for the state in `t`, without
+    /// advancing any actuator. Integrators call this at intermediate stages.
+    pub fn compute_forces(&mut self, t: &Transform) {
+        self.common_moment = Vec3::ZERO;
+        self.common_force = Vec3::ZERO;
+        self.draw_vecs = Vec::new();
 
+        // --- Aerodynamics ---
         let mach = self.airspeed.length() / self.speed_of_sound;
         self.mach = mach;
 
@@ -415,17 +617,17 @@ impl FlightModel {
         let at = &aero.tables;
 
         let cy_alpha = table_lerp(&at.mach, &at.Cya, mach);
-        let cx0 = table_lerp(&at.mach, &at.cx0, mach);
+        let cx = match &at.Cx {
+            Some(cx) => cx.lookup(&[self.alpha, mach]),
+            None => table_lerp(&at.mach, &at.cx0, mach) + self.wave_drag(mach),
+        };
         let cy_max = table_lerp(&at.mach, &at.CyMax, mach) + aero.cy_flap * 0.4 * self.slats_pos;
         let alpha_max = table_lerp(&at.mach, &at.Aldop, mach);
-        let omx_max = table_lerp(&at.mach, &at.OmxMax, mach);
+        self.control_effectiveness = self.control_effectiveness(mach);
 
-        let cy = limit(cy_alpha * self.alpha, -cy_max, cy_max);
         let cy_tail = limit((0.5 * cy_alpha + aero.Czbe) * self.beta, -cy_max, cy_max);
 
-        let q = 0.5
-            * table_lerp(&ALTITUDE_M, &AIR_DENSITY_KG_PER_M3, self.altitude_asl)
-            * self.airspeed.length_squared();
+        let q = 0.5 * self.atmosphere_density * self.airspeed.length_squared();
 
         let aos = self.beta.to_radians();
         let aoa = self.alpha.to_radians();
@@ -435,41 +637,69 @@ impl FlightModel {
         // AERODYNAMICS
         // =================================================
 
-        let lift = cy + aero.Cy0 + (aero.cy_flap * self.flaps_pos);
-        let drag = cx0
+        let lift = aero.Cy0 + (aero.cy_flap * self.flaps_pos);
+        let drag = cx
             + (aero.cx_brk * self.airbrake_pos)
             + (aero.cx_flap * self.flaps_pos)
             + (aero.cx_gear * self.gear_pos);
 
-        self.update_wings(alpha_max, lift, drag, q, s, aos, aoa, t, cy_tail);
+        let left = self.wing_coefficients(self.left_wing_pos, cy_alpha, cy_max, lift, drag);
+        let right = self.wing_coefficients(self.right_wing_pos, cy_alpha, cy_max, lift, drag);
+        self.stall = (left.stall + right.stall) / 2.0;
+
+        self.update_wings(alpha_max, left, right, q, s, aos, aoa, t, cy_tail);
 
-        self.update_elevator(aoa, q, &mach, t);
+        self.update_elevator(aoa, q);
 
-        self.update_roll(aos, aoa, q, t);
+        self.update_roll(q);
 
-        self.update_yaw(aos, q, t);
+        self.update_yaw(aos, q);
 
-        self.update_other(q, omx_max, mach, aos);
+        self.update_other(mach);
 
         // =================================================
         // General
         // =================================================
-        self.sim_engine(&mach, &dt, t);
+        self.sim_engine(&mach, t);
 
         let l_grav = (global_to_local(GRAV, t.rotation)) * self.current_mass;
         self.add_local_force(l_grav, self.center_of_mass);
 
-        self.wheel_on_ground(t.rotation, &t);
+        self.ground_forces(t);
+        self.hook_force(t);
+        self.catapult_force(t);
+    }
+
+    /// Commits what happened over the step of `dt` just integrated, from the state in `t`.
+    /// Runs once per step, never at the intermediate stages of an integrator.
+    pub fn update_events(&mut self, dt: f32, t: &Transform) {
+        self.update_contacts(dt, t);
+        self.update_hook(t);
+        self.update_catapult(t);
     }
 
     pub fn update_variables(&mut self, transform: &Transform) {
+        self.altitude_asl = transform.translation.y;
+        self.altitude_agl = self.altitude_asl - self.ground_sample(transform.translation).height;
+
+        let air = self.atmosphere.sample(self.altitude_asl);
+        self.atmosphere_temperature = air.temperature;
+        self.atmosphere_pressure = air.pressure;
+        self.atmosphere_density = air.density;
+        self.speed_of_sound = air.speed_of_sound;
+
+        self.wind = self.wind_field.wind_at(self.altitude_agl, self.fm_clock)
+            + local_to_global(self.turbulence.velocity, transform.rotation);
+
         self.velocity_local = global_to_local(self.velocity, transform.rotation);
+        self.airspeed = global_to_local(self.velocity - self.wind, transform.rotation);
 
-        let v_forward = -self.velocity_local.z; // Negate because forward is -Z
-        let v_right = self.velocity_local.x;
-        let v_up = self.velocity_local.y;
+        // Aerodynamic angles come from the air-relative velocity
+        let v_forward = -self.airspeed.z; // Negate because forward is -Z
+        let v_right = self.airspeed.x;
+        let v_up = self.airspeed.y;
 
-        if self.velocity.length() > 10.0 {
+        if self.airspeed.length() > 10.0 {
             self.alpha = -v_up.atan2(v_forward).to_degrees();
             self.beta = v_right.atan2(v_forward).to_degrees();
         } else {
@@ -477,119 +707,24 @@ impl FlightModel {
             self.beta = 0.0;
         }
 
-        let (yaw, pitch, roll) = transform.rotation.to_euler(EulerRot::ZYX);
-        self.heading = yaw.to_degrees(); // Store in degrees to match alpha/beta
-        self.pitch = pitch.to_degrees();
-        self.roll = roll.to_degrees(); // Store in degrees to be consistent
-
-        self.roll_rate = -self.angular_velocity.z;
-        self.yaw_rate = self.angular_velocity.y;
-        self.pitch_rate = self.angular_velocity.x;
-
-        let ay = self.common_force.y / self.current_mass;
-        self.g = (ay / 9.81) + 1.0;
-
-        self.altitude_asl = transform.translation.y;
-    }
-
-    fn wheel_on_ground(&mut self, gr: Quat, transform: &Transform) {
-        let wheels = self.plane_config.structure;
-
-        let fwp = local_to_global(wheels.front_wheel, gr) + transform.translation;
-        let blwp = local_to_global(wheels.back_left_wheel, gr) + transform.translation;
-        let brwp = local_to_global(wheels.back_right_wheel, gr) + transform.translation;
-
-        let ground_height = 0.0;
-        let wheel_radius = 0.3; // Adjust to match your wheel size
-
-        self.check_wheel(
-            fwp,
-            wheels.front_wheel,
-            ground_height,
-            wheel_radius,
-            gr,
-            1.0,
-        );
-        self.check_wheel(
-            blwp,
-            wheels.back_left_wheel,
-            ground_height,
-            wheel_radius,
-            gr,
-            1.0,
-        );
-        self.check_wheel(
-            brwp,
-            wheels.back_right_wheel,
-            ground_height,
-            wheel_radius,
-            gr,
-            1.0,
-        );
-    }
-
-    fn check_wheel(
-        &mut self,
-        wheel_pos: Vec3,
-        local_pos: Vec3,
-        ground_height: f32,
-        wheel_radius: f32,
-        rotation: Quat,
-        multi: f32,
-    ) {
-        let wheel_bottom = wheel_pos.y - wheel_radius;
-        let penetration = ground_height - wheel_bottom;
-
-        let damping = 50000.0;
-        let spring_k = 2000000.0;
-
-        let damping_force = -damping * self.velocity.y;
-
-        if penetration > 0.0 {
-            self.on_ground = true;
-            let total = damping_force + spring_k * penetration;
-            let upward_force = global_to_local(Vec3::new(0.0, total, 0.0), rotation);
-            self.add_local_force(upward_force * multi, local_pos);
-        } else {
-            self.on_ground = false;
-        }
-    }
-
-    pub fn transform(&mut self, dt: f32, transform: &mut Transform) {
-        // --- Linear motion ---
-        let local_acceleration = self.common_force / self.current_mass;
-
-        let global_acceleration = local_to_global(local_acceleration, transform.rotation);
-
-        self.velocity += global_acceleration * dt;
-
-        transform.translation += self.velocity * dt;
-
-        // --- Angular motion ---
-        let angular_acceleration = vec3(
-            self.common_moment.x / self.moment_of_inertia[0],
-            self.common_moment.y / self.moment_of_inertia[1],
-            self.common_moment.z / self.moment_of_inertia[2],
-        );
-
-        self.angular_velocity += angular_acceleration * dt;
-
-        let world_angular_velocity = local_to_global(self.angular_velocity, transform.rotation);
-
-        let angle = world_angular_velocity.length() * dt;
-        if angle > 0.00001 {
-            let axis = world_angular_velocity.normalize();
-            let delta_rot = Quat::from_axis_angle(axis, angle);
-
-            transform.rotation = (delta_rot * transform.rotation).normalize();
-        }
+        // Degrees, to match alpha/beta. World -Z is true north, so the heading is true.
+        let attitude = frames::attitude(transform.rotation);
+        self.heading = attitude.heading;
+        self.pitch = attitude.pitch;
+        self.roll = attitude.bank;
+        self.true_heading = attitude.heading;
+        self.magnetic_heading = self.geo.magnetic_heading(self.true_heading);
+
+        let rates = frames::body_rates(self.angular_velocity);
+        self.roll_rate = rates.x;
+        self.pitch_rate = rates.y;
+        self.yaw_rate = rates.z;
     }
 
     pub fn start_hot(&mut self, vel: Vec3) {
         // Landing gear up
         if vel == Vec3::ZERO {
             self.gear_switch = true;
-            self.gear_pos = 1.0;
 
             self.left_engine_switch = true;
             self.left_throttle_input = 0.5;
@@ -598,7 +733,6 @@ impl FlightModel {
             self.right_throttle_input = 0.5;
         } else {
             self.gear_switch = false;
-            self.gear_pos = 0.0;
 
             self.left_engine_switch = true;
             self.left_throttle_input = 0.9;
@@ -607,14 +741,36 @@ impl FlightModel {
             self.right_throttle_input = 0.9;
         }
 
-        self.current_mass = 10000.0;
+        self.gear_pos = self.gear_switch as u8 as f32;
+        for leg in &mut self.gear_legs {
+            leg.extension = self.gear_pos;
+        }
+
         self.velocity = vel;
 
-        //Engines on at 50% throttle
-        self.left_throttle_output = 0.5;
-        self.left_engine_power_readout = 0.5;
+        // Engines already spooled to the selected throttle
+        let et = &self.plane_config.engine.tables;
+        let ambient = self.atmosphere_temperature - 273.15;
+        self.left_engine.start_hot(
+            table_lerp(
+                &et.throttle_input,
+                &et.engine_power_readout,
+                self.left_throttle_input,
+            ),
+            ambient,
+        );
+        self.right_engine.start_hot(
+            table_lerp(
+                &et.throttle_input,
+                &et.engine_power_readout,
+                self.right_throttle_input,
+            ),
+            ambient,
+        );
 
-        self.right_throttle_output = 0.5;
-        self.right_engine_power_readout = 0.5;
+        self.left_throttle_output = self.engine_power(&self.left_engine);
+        self.left_engine_power_readout = self.left_engine.rpm;
+        self.right_throttle_output = self.engine_power(&self.right_engine);
+        self.right_engine_power_readout = self.right_engine.rpm;
     }
 }
@@ -0,0 +1,84 @@
+use crate::plane::plane_config::Fuel;
+
+#[derive(Debug, Clone)]
+pub struct FuelTank {
+    pub fuel: f32, // kg
+    pub external: bool,
+    pub priority: u8,
+}
+
+/// Fuel tanks and feed logic. Tanks with the lowest `priority` drain first;
+/// tanks sharing a priority drain evenly.
+#[derive(Debug, Clone, Default)]
+pub struct FuelSystem {
+    pub tanks: Vec<FuelTank>,
+}
+
+impl FuelSystem {
+    pub fn new(config: &Fuel) -> Self {
+        let tanks = config
+            .tanks
+            .iter()
+            .map(|tank| FuelTank {
+                fuel: tank.initial.min(tank.capacity),
+                external: tank.external,
+                priority: tank.priority,
+            })
+            .collect();
+
+        Self { tanks }
+    }
+
+    pub fn internal(&self) -> f32 {
+        self.tanks
+            .iter()
+            .filter(|t| !t.external)
+            .map(|t| t.fuel)
+            .sum()
+    }
+
+    pub fn external(&self) -> f32 {
+        self.tanks
+            .iter()
+            .filter(|t| t.external)
+            .map(|t| t.fuel)
+            .sum()
+    }
+
+    pub fn total(&self) -> f32 {
+        self.tanks.iter().map(|t| t.fuel).sum()
+    }
+
+    /// Removes up to `amount` kg following the feed order and returns what was burned.
+    pub fn burn(&mut self, amount: f32) -> f32 {
+        let mut remaining = amount.max(0.0);
+
+        while remaining > 0.0 {
+            let Some(priority) = self
+                .tanks
+                .iter()
+                .filter(|t| t.fuel > 0.0)
+                .map(|t| t.priority)
+                .min()
+            else {
+                break;
+            };
+
+            let group_fuel: f32 = self
+                .tanks
+                .iter()
+                .filter(|t| t.priority == priority)
+                .map(|t| t.fuel)
+                .sum();
+            let taken = remaining.min(group_fuel);
+
+            for tank in self.tanks.iter_mut().filter(|t| t.priority == priority) {
+                tank.fuel = (tank.fuel - taken * tank.fuel / group_fuel).max(0.0);
+            }
+
+            remaining -= taken;
+        }
+
+        amount.max(0.0) - remaining
+    }
+}
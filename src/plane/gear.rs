@@ -0,0 +1,339 @@
+use crate::plane::damage::Part;
+use crate::plane::flight_model::FlightModel;
+use crate::plane::flight_physics::{global_to_local, local_to_global};
+use crate::plane::plane_config::{Structure, Strut};
+use crate::util::{actuator, limit, rad};
+use crate::world::terrain::GroundSample;
+use bevy::prelude::*;
+
+const GEAR_RATE: f32 = 0.06;
+// Past its travel a strut hits the stop, which is this many times stiffer than the spring
+const BOTTOM_OUT_FACTOR: f32 = 20.0;
+
+// Tyre friction coefficients on a dry runway
+const ROLLING_FRICTION: f32 = 0.02;
+const PEAK_FRICTION: f32 = 0.8;
+const SLIDING_FRICTION: f32 = 0.5;
+// Friction demanded by a fully pressed brake, the wheel locks once it exceeds the peak
+const BRAKE_FRICTION: f32 = 1.0;
+// Share of the peak friction kept while anti-skid cycles the brake pressure
+const ANTI_SKID_EFFICIENCY: f32 = 0.9;
+
+// Slip angle, in radians, at which the tyre side force saturates
+const PEAK_SLIP_ANGLE: f32 = 0.14;
+// Keeps the slip angle and friction direction finite at standstill, in m/s
+const SLIP_REFERENCE_SPEED: f32 = 1.0;
+const FRICTION_SPEED: f32 = 0.05;
+
+// Nosewheel authority is full while taxiing and fades out over the takeoff roll
+const NOSEWHEEL_MAX_ANGLE: f32 = 30.0;
+const NOSEWHEEL_HIGH_SPEED_ANGLE: f32 = 5.0;
+const NOSEWHEEL_FADE_START: f32 = 10.0;
+const NOSEWHEEL_FADE_END: f32 = 40.0;
+
+// Fuselage skin scraping along the runway
+const HULL_STIFFNESS: f32 = 5000000.0;
+const HULL_DAMPING: f32 = 200000.0;
+const HULL_FRICTION: f32 = 0.45;
+// Integrity a wing, engine or the tail loses per meter it is dragged along the ground
+const STRIKE_DAMAGE: f32 = 0.05;
+
+/// State of one landing gear leg, in the order front, back left, back right
+#[derive(Debug, Default, Copy, Clone)]
+pub struct GearLeg {
+    /// 0 retracted to 1 down and locked
+    pub extension: f32,
+    pub compression: f32,
+    pub collapsed: bool,
+}
+
+/// Tyre of a down and locked leg pushed into the ground
+struct WheelContact {
+    penetration: f32,
+    normal: Vec3,
+    /// Tyre patch in body coordinates
+    pos: Vec3,
+    /// Velocity of the tyre patch relative to the surface
+    velocity: Vec3,
+    load: f32,
+}
+
+/// Airframe point pushed into the ground
+struct HullContact {
+    penetration: f32,
+    normal: Vec3,
+    /// Velocity of the point relative to the surface
+    velocity: Vec3,
+}
+
+struct Wheel {
+    pos: Vec3,
+    strut: Strut,
+    steering: f32,
+    brake: f32,
+}
+
+/// Airframe points that can touch the ground, in body coordinates, with the part a strike
+/// there damages
+fn hull_points(structure: Structure) -> impl Iterator<Item = (Vec3, Option<Part>)> {
+    // Only reached by the ground with the gear up or collapsed
+    let belly = structure.belly_contacts.map(|point| (point, None));
+    belly.into_iter().chain([
+        // Airframe extremities, touching with any of these at speed is usually a crash
+        (structure.nose_contact, None),
+        (structure.tail_contact, Some(Part::Tail)),
+        (structure.left_wingtip_contact, Some(Part::LeftWing)),
+        (structure.right_wingtip_contact, Some(Part::RightWing)),
+        (structure.left_engine_contact, Some(Part::LeftEngine)),
+        (structure.right_engine_contact, Some(Part::RightEngine)),
+    ])
+}
+
+/// Skin stiffness, damping and scraping friction of a hull contact, in body coordinates
+fn hull_force(contact: &HullContact, rotation: Quat) -> Vec3 {
+    let normal = contact.normal;
+    let closing_speed = -contact.velocity.dot(normal);
+    let load = (HULL_STIFFNESS * contact.penetration + HULL_DAMPING * closing_speed).max(0.0);
+
+    let slide = contact.velocity.reject_from_normalized(normal);
+    let friction = -slide.normalize_or_zero()
+        * HULL_FRICTION
+        * load
+        * limit(slide.length() / FRICTION_SPEED, 0.0, 1.0);
+
+    global_to_local(normal * load + friction, rotation)
+}
+
+impl FlightModel {
+    pub(crate) fn update_gear(&mut self, dt: f32) {
+        let target = self.gear_switch as u8 as f32;
+        let overspeed = self.airspeed.length() > self.plane_config.structure.gear_limit_speed
+            && !self.invincible;
+        for leg in &mut self.gear_legs {
+            // Lowering a leg into the airflow past the limit speed tears it off
+            if overspeed && target > leg.extension {
+                leg.collapsed = true;
+            }
+            leg.extension = limit(
+                actuator(leg.extension, target, -GEAR_RATE * dt, GEAR_RATE * dt),
+                0.0,
+                1.0,
+            );
+        }
+
+        // Drag and the gauge follow the legs on average
+        self.gear_pos = self.gear_legs.iter().map(|leg| leg.extension).sum::<f32>()
+            / self.gear_legs.len() as f32;
+    }
+
+    fn wheels(&self) -> [Wheel; 3] {
+        let structure = self.plane_config.structure;
+        let steering = self.nosewheel_angle();
+        let brake = limit(self.wheel_brake, 0.0, 1.0);
+
+        [
+            Wheel {
+                pos: structure.front_wheel,
+                strut: structure.front_strut,
+                steering,
+                brake: 0.0,
+            },
+            Wheel {
+                pos: structure.back_left_wheel,
+                strut: structure.back_left_strut,
+                steering: 0.0,
+                brake,
+            },
+            Wheel {
+                pos: structure.back_right_wheel,
+                strut: structure.back_right_strut,
+                steering: 0.0,
+                brake,
+            },
+        ]
+    }
+
+    /// Adds the tyre and airframe ground forces for the state in `t`. Integrators call this
+    /// at intermediate stages, so it leaves the gear alone and `update_contacts` commits
+    /// what the contacts did once the step is done.
+    pub(crate) fn ground_forces(&mut self, t: &Transform) {
+        for (leg, wheel) in self.wheels().into_iter().enumerate() {
+            if let Some(contact) = self.wheel_contact(leg, &wheel, t) {
+                let force = self.tyre_force(&wheel, &contact, t.rotation);
+                self.add_local_force(force, contact.pos);
+            }
+        }
+
+        for (point, _) in hull_points(self.plane_config.structure) {
+            if let Some(contact) = self.hull_contact(point, t) {
+                self.add_local_force(hull_force(&contact, t.rotation), point);
+            }
+        }
+    }
+
+    /// Collapses overloaded legs, damages parts striking the ground, checks for a crash and
+    /// refreshes `on_ground` for the state after a step of `dt`
+    pub(crate) fn update_contacts(&mut self, dt: f32, t: &Transform) {
+        self.on_ground = false;
+        for (leg, wheel) in self.wheels().into_iter().enumerate() {
+            self.gear_legs[leg].compression = 0.0;
+            let Some(contact) = self.wheel_contact(leg, &wheel, t) else {
+                continue;
+            };
+            self.on_ground = true;
+            self.gear_legs[leg].compression = contact.penetration.min(wheel.strut.travel);
+            if contact.load > wheel.strut.max_load && !self.invincible {
+                self.gear_legs[leg].collapsed = true;
+            }
+        }
+
+        for (point, part) in hull_points(self.plane_config.structure) {
+            if let Some(contact) = self.hull_contact(point, t) {
+                self.on_ground = true;
+                if let Some(part) = part {
+                    self.apply_damage(part, STRIKE_DAMAGE * contact.velocity.length() * dt);
+                }
+                let closing_speed = -contact.velocity.dot(contact.normal);
+                self.check_crash(closing_speed, contact.normal, t.rotation);
+            }
+        }
+    }
+
+    /// Nosewheel deflection in radians, positive turns the nose left like the rudder
+    pub fn nosewheel_angle(&self) -> f32 {
+        let speed = self.velocity_local.length();
+        let fade = limit(
+            (speed - NOSEWHEEL_FADE_START) / (NOSEWHEEL_FADE_END - NOSEWHEEL_FADE_START),
+            0.0,
+            1.0,
+        );
+        let max_angle =
+            NOSEWHEEL_MAX_ANGLE + (NOSEWHEEL_HIGH_SPEED_ANGLE - NOSEWHEEL_MAX_ANGLE) * fade;
+
+        self.rudder_command * rad(max_angle)
+    }
+
+    /// Strut load and tyre friction of a wheel contact, in body coordinates
+    fn tyre_force(&self, wheel: &Wheel, contact: &WheelContact, rotation: Quat) -> Vec3 {
+        let normal = contact.normal;
+        let contact_velocity = contact.velocity;
+        let load = contact.load;
+
+        // Tyre axes on the ground plane, turned by the nosewheel steering
+        let heading = local_to_global(
+            Quat::from_rotation_y(wheel.steering) * Vec3::NEG_Z,
+            rotation,
+        );
+        let roll_dir = heading.reject_from_normalized(normal).normalize_or_zero();
+        let side_dir = normal.cross(roll_dir);
+
+        let roll_speed = contact_velocity.dot(roll_dir);
+        let side_speed = contact_velocity.dot(side_dir);
+
+        let braking = wheel.brake * BRAKE_FRICTION;
+        let friction = if braking > PEAK_FRICTION && !self.anti_skid {
+            // Locked tyre skids along its ground track and gives no cornering force
+            let slide = roll_dir * roll_speed + side_dir * side_speed;
+            let slide_speed = slide.length();
+            -slide.normalize_or_zero()
+                * SLIDING_FRICTION
+                * load
+                * limit(slide_speed / FRICTION_SPEED, 0.0, 1.0)
+        } else {
+            let braking = braking.min(PEAK_FRICTION * ANTI_SKID_EFFICIENCY);
+            let longitudinal = -(ROLLING_FRICTION + braking)
+                * load
+                * limit(roll_speed / FRICTION_SPEED, -1.0, 1.0);
+
+            let slip_angle = side_speed.atan2(roll_speed.abs() + SLIP_REFERENCE_SPEED);
+            let lateral = -PEAK_FRICTION * load * limit(slip_angle / PEAK_SLIP_ANGLE, -1.0, 1.0);
+
+            // Friction circle, braking and cornering share the same grip
+            (roll_dir * longitudinal + side_dir * lateral).clamp_length_max(PEAK_FRICTION * load)
+        };
+
+        global_to_local(normal * load + friction, rotation)
+    }
+
+    /// Tyre of `leg` pushed into the ground, `None` while it is clear or the leg isn't down
+    /// and locked
+    fn wheel_contact(&self, leg: usize, wheel: &Wheel, t: &Transform) -> Option<WheelContact> {
+        let gear_leg = self.gear_legs[leg];
+        if gear_leg.collapsed || gear_leg.extension < 1.0 {
+            return None;
+        }
+
+        let rotation = t.rotation;
+        let strut = wheel.strut;
+        let wheel_pos = local_to_global(wheel.pos, rotation) + t.translation;
+
+        let ground = self.ground_sample(wheel_pos);
+        let normal = ground.normal;
+
+        // Depth of the tyre below the surface, measured along the surface normal
+        let wheel_bottom = wheel_pos.y - strut.wheel_radius;
+        let penetration = (ground.height - wheel_bottom) * normal.y;
+        if penetration <= 0.0 {
+            return None;
+        }
+
+        let pos = wheel.pos + global_to_local(-normal * strut.wheel_radius, rotation);
+        let velocity = self.point_velocity(pos, rotation) - ground.velocity;
+
+        let mut load = strut.stiffness * penetration - strut.damping * velocity.dot(normal);
+        if penetration > strut.travel {
+            load += strut.stiffness * BOTTOM_OUT_FACTOR * (penetration - strut.travel);
+        }
+
+        Some(WheelContact {
+            penetration,
+            normal,
+            pos,
+            velocity,
+            load: load.max(0.0),
+        })
+    }
+
+    /// Airframe point pushed into the ground, `None` while it is clear
+    fn hull_contact(&self, point: Vec3, t: &Transform) -> Option<HullContact> {
+        let point_pos = local_to_global(point, t.rotation) + t.translation;
+        let ground = self.ground_sample(point_pos);
+        let normal = ground.normal;
+
+        let penetration = (ground.height - point_pos.y) * normal.y;
+        (penetration > 0.0).then(|| HullContact {
+            penetration,
+            normal,
+            velocity: self.point_velocity(point, t.rotation) - ground.velocity,
+        })
+    }
+
+    /// Destroys the aircraft when the airframe hits the ground too fast or too far from upright
+    fn check_crash(&mut self, sink_speed: f32, normal: Vec3, rotation: Quat) {
+        if self.crashed || self.invincible {
+            return;
+        }
+
+        let structure = &self.plane_config.structure;
+        let upright = local_to_global(Vec3::Y, rotation).dot(normal);
+        if sink_speed > structure.crash_sink_speed || upright < rad(structure.crash_tilt).cos() {
+            self.crashed = true;
+            self.impact_speed = self.velocity.length();
+        }
+    }
+
+    /// Surface under a world position, a carrier deck if there is one there
+    pub fn ground_sample(&self, pos: Vec3) -> GroundSample {
+        self.decks
+            .iter()
+            .find_map(|deck| deck.sample(pos))
+            .unwrap_or_else(|| self.terrain.sample(pos.x, pos.z))
+    }
+
+    /// World velocity of a point given in body coordinates
+    pub(crate) fn point_velocity(&self, pos: Vec3, rotation: Quat) -> Vec3 {
+        let arm = local_to_global(pos - self.center_of_mass, rotation);
+        let spin = local_to_global(self.angular_velocity, rotation);
+        self.velocity + spin.cross(arm)
+    }
+}
@@ -0,0 +1,215 @@
+use crate::plane::flight_model::FlightModel;
+use crate::plane::flight_physics::{global_to_local, local_to_global};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Scheme used by `FlightModel::transform` to advance the rigid body state.
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    /// Position and attitude advance with the velocities from the start of the step.
+    ExplicitEuler,
+    /// Velocities advance first and the new values move position and attitude.
+    #[default]
+    SemiImplicitEuler,
+    /// Classic 4th order Runge-Kutta, re-evaluating forces at every stage.
+    Rk4,
+}
+
+/// Rigid body state integrated by the flight model
+#[derive(Debug, Copy, Clone)]
+struct BodyState {
+    translation: Vec3,
+    velocity: Vec3,
+    rotation: Quat,
+    angular_velocity: Vec3,
+}
+
+/// Time derivative of a `BodyState`
+#[derive(Debug, Copy, Clone)]
+struct BodyRate {
+    velocity: Vec3,
+    acceleration: Vec3,
+    spin: Quat,
+    angular_acceleration: Vec3,
+}
+
+impl BodyState {
+    fn step(&self, rate: &BodyRate, dt: f32) -> Self {
+        Self {
+            translation: self.translation + rate.velocity * dt,
+            velocity: self.velocity + rate.acceleration * dt,
+            rotation: (self.rotation + rate.spin * dt).normalize(),
+            angular_velocity: self.angular_velocity + rate.angular_acceleration * dt,
+        }
+    }
+}
+
+/// Quaternion derivative for a body-frame angular velocity
+fn spin(rotation: Quat, angular_velocity: Vec3) -> Quat {
+    let w = local_to_global(angular_velocity, rotation);
+    Quat::from_xyzw(w.x, w.y, w.z, 0.0) * rotation * 0.5
+}
+
+/// Rotates `rotation` by the body-frame angular velocity over `dt`.
+fn rotate(rotation: Quat, angular_velocity: Vec3, dt: f32) -> Quat {
+    let world_angular_velocity = local_to_global(angular_velocity, rotation);
+
+    let angle = world_angular_velocity.length() * dt;
+    if angle > 0.00001 {
+        let axis = world_angular_velocity.normalize();
+        let delta_rot = Quat::from_axis_angle(axis, angle);
+
+        (delta_rot * rotation).normalize()
+    } else {
+        rotation
+    }
+}
+
+/// Cross product matrix, so that `skew(a) * b == a.cross(b)`
+fn skew(v: Vec3) -> Mat3 {
+    Mat3::from_cols(
+        vec3(0.0, v.z, -v.y),
+        vec3(-v.z, 0.0, v.x),
+        vec3(v.y, -v.x, 0.0),
+    )
+}
+
+/// Builds the body-frame inertia tensor from `[Ix, Iy, Iz, Ixy]`.
+///
+/// The diagonal terms are about the local pitch (x), yaw (y) and roll (z) axes.
+/// `Ixy` is the roll-yaw product of inertia in the usual forward/up body axes,
+/// so it lands in the y-z slot with its sign flipped because local z points aft.
+pub fn inertia_tensor(moment_of_inertia: [f32; 4]) -> Mat3 {
+    let [ix, iy, iz, ixy] = moment_of_inertia;
+    Mat3::from_cols(vec3(ix, 0.0, 0.0), vec3(0.0, iy, -ixy), vec3(0.0, -ixy, iz))
+}
+
+impl FlightModel {
+    /// Euler's rigid body equations: I * dw/dt = M - w x (I * w)
+    pub fn angular_acceleration(&self) -> Vec3 {
+        let w = self.angular_velocity;
+        let gyroscopic = w.cross(self.inertia * w);
+        self.inverse_inertia * (self.common_moment - gyroscopic)
+    }
+
+    /// Applies the gyroscopic term over `dt` with one implicit Newton step,
+    /// which stays stable at the high rotation rates of a tumbling aircraft.
+    fn solve_gyroscopic(&mut self, dt: f32) {
+        let w = self.angular_velocity;
+        let residual = w.cross(self.inertia * w) * dt;
+        let jacobian = self.inertia + (skew(w) * self.inertia - skew(self.inertia * w)) * dt;
+        self.angular_velocity = w - jacobian.inverse() * residual;
+    }
+
+    fn linear_acceleration(&self, rotation: Quat) -> Vec3 {
+        local_to_global(self.common_force / self.current_mass, rotation)
+    }
+
+    /// Derivative from the forces already accumulated in `common_force` / `common_moment`
+    fn current_rate(&self, state: &BodyState) -> BodyRate {
+        BodyRate {
+            velocity: state.velocity,
+            acceleration: self.linear_acceleration(state.rotation),
+            spin: spin(state.rotation, state.angular_velocity),
+            angular_acceleration: self.angular_acceleration(),
+        }
+    }
+
+    /// Rebuilds the forces for `state` and returns its derivative.
+    fn evaluate(&mut self, state: &BodyState) -> BodyRate {
+        let t = Transform {
+            translation: state.translation,
+            rotation: state.rotation,
+            ..default()
+        };
+        self.velocity = state.velocity;
+        self.angular_velocity = state.angular_velocity;
+
+        self.update_variables(&t);
+        self.compute_forces(&t);
+        self.current_rate(state)
+    }
+
+    /// Sets the g-load readout from the acceleration a step applied. Done here rather than
+    /// from `common_force`, which RK4 rebuilds at every stage.
+    fn record_load_factor(&mut self, acceleration: Vec3, rotation: Quat) {
+        self.g = global_to_local(acceleration, rotation).y / 9.81 + 1.0;
+    }
+
+    /// Integrates the forces from the last `simulate` call over `dt`.
+    pub fn transform(&mut self, dt: f32, transform: &mut Transform) {
+        match self.integrator {
+            Integrator::ExplicitEuler => self.explicit_euler(dt, transform),
+            Integrator::SemiImplicitEuler => self.semi_implicit_euler(dt, transform),
+            Integrator::Rk4 => self.rk4(dt, transform),
+        }
+    }
+
+    fn explicit_euler(&mut self, dt: f32, transform: &mut Transform) {
+        let acceleration = self.linear_acceleration(transform.rotation);
+        let angular_acceleration = self.angular_acceleration();
+        self.record_load_factor(acceleration, transform.rotation);
+
+        transform.translation += self.velocity * dt;
+        transform.rotation = rotate(transform.rotation, self.angular_velocity, dt);
+
+        self.velocity += acceleration * dt;
+        self.angular_velocity += angular_acceleration * dt;
+    }
+
+    fn semi_implicit_euler(&mut self, dt: f32, transform: &mut Transform) {
+        // --- Linear motion ---
+        let acceleration = self.linear_acceleration(transform.rotation);
+        self.record_load_factor(acceleration, transform.rotation);
+        self.velocity += acceleration * dt;
+        transform.translation += self.velocity * dt;
+
+        // --- Angular motion ---
+        self.angular_velocity += self.inverse_inertia * self.common_moment * dt;
+        self.solve_gyroscopic(dt);
+        transform.rotation = rotate(transform.rotation, self.angular_velocity, dt);
+    }
+
+    fn rk4(&mut self, dt: f32, transform: &mut Transform) {
+        let start = BodyState {
+            translation: transform.translation,
+            velocity: self.velocity,
+            rotation: transform.rotation,
+            angular_velocity: self.angular_velocity,
+        };
+        let start_force = self.common_force;
+        let start_moment = self.common_moment;
+
+        let k1 = self.current_rate(&start);
+        let k2 = self.evaluate(&start.step(&k1, dt / 2.0));
+        let k3 = self.evaluate(&start.step(&k2, dt / 2.0));
+        let k4 = self.evaluate(&start.step(&k3, dt));
+
+        let weighted = BodyRate {
+            velocity: (k1.velocity + 2.0 * k2.velocity + 2.0 * k3.velocity + k4.velocity) / 6.0,
+            acceleration: (k1.acceleration
+                + 2.0 * k2.acceleration
+                + 2.0 * k3.acceleration
+                + k4.acceleration)
+                / 6.0,
+            spin: (k1.spin + k2.spin * 2.0 + k3.spin * 2.0 + k4.spin) * (1.0 / 6.0),
+            angular_acceleration: (k1.angular_acceleration
+                + 2.0 * k2.angular_acceleration
+                + 2.0 * k3.angular_acceleration
+                + k4.angular_acceleration)
+                / 6.0,
+        };
+        let end = start.step(&weighted, dt);
+        self.record_load_factor(weighted.acceleration, start.rotation);
+
+        transform.translation = end.translation;
+        transform.rotation = end.rotation;
+        self.velocity = end.velocity;
+        self.angular_velocity = end.angular_velocity;
+
+        // Keep the readouts consistent with the forces that started the step
+        self.common_force = start_force;
+        self.common_moment = start_moment;
+    }
+}
@@ -8,20 +8,34 @@ pub struct Plane {
 }
 
 impl Plane {
-    pub fn new(plane_type: &str, vel: Vec3) -> Self {
-        let plane_config = load_config(plane_type);
+    pub fn new(plane_type: &str, vel: Vec3) -> Result<Self, ConfigError> {
+        let plane_config = load_config(plane_type)?;
         let mut flight_model = FlightModel::new(plane_config);
         flight_model.start_hot(vel);
 
-        Plane {
+        Ok(Plane {
             flight_model: flight_model,
-        }
+        })
     }
 
-    pub fn simulate(&mut self, dt: f32, transform: &mut Transform) {
+    /// Advances the flight model by one fixed step, split into `substeps` equal parts.
+    pub fn simulate(&mut self, dt: f32, substeps: u32, transform: &mut Transform) {
+        let substeps = substeps.max(1);
+        let sub_dt = dt / substeps as f32;
+        if self.flight_model.crashed {
+            // The wreck stays where it hit, the game decides what happens next
+            return;
+        }
         self.flight_model.update_variables(transform);
-        self.flight_model.simulate(dt, transform);
-        self.flight_model.transform(dt, transform);
+        for _ in 0..substeps {
+            self.flight_model.simulate(sub_dt, transform);
+            self.flight_model.transform(sub_dt, transform);
+            self.flight_model.update_variables(transform);
+            self.flight_model.update_events(sub_dt, transform);
+            if self.flight_model.crashed {
+                break;
+            }
+        }
     }
 
     pub fn gamepad_input(&mut self, gamepad: &Gamepad) {
@@ -50,12 +64,12 @@ impl Plane {
         controls.roll_input = limit(move_x * mul, -1.0, 1.0);
     }
 
-    pub fn input(&mut self, keyboard: &Res<ButtonInput<KeyCode>>) {
+    pub fn input(&mut self, keyboard: &Res<ButtonInput<KeyCode>>, dt: f32) {
         const TRIM_PITCH_STEP: f32 = 0.0015;
         const TRIM_ROLL_STEP: f32 = 0.001;
         const TRIM_YAW_STEP: f32 = 0.001;
 
-        const THROTTLE_STEP: f32 = 0.0075;
+        const THROTTLE_RATE: f32 = 0.45; // per second
 
         // const INPUT_MIN: f32 = -1.0;
         // const INPUT_MAX: f32 = 1.0;
@@ -128,35 +142,35 @@ impl Plane {
         // --- Throttle ---
         if keyboard.pressed(KeyCode::KeyW) {
             controls.left_throttle_input = limit(
-                controls.left_throttle_input + THROTTLE_STEP,
+                controls.left_throttle_input + THROTTLE_RATE * dt,
                 THROTTLE_MIN,
                 THROTTLE_MAX,
             );
             controls.right_throttle_input = limit(
-                controls.right_throttle_input + THROTTLE_STEP,
+                controls.right_throttle_input + THROTTLE_RATE * dt,
                 THROTTLE_MIN,
                 THROTTLE_MAX,
             );
         } else if keyboard.pressed(KeyCode::KeyS) {
             controls.left_throttle_input = limit(
-                controls.left_throttle_input - THROTTLE_STEP,
+                controls.left_throttle_input - THROTTLE_RATE * dt,
                 THROTTLE_MIN,
                 THROTTLE_MAX,
             );
             controls.right_throttle_input = limit(
-                controls.right_throttle_input - THROTTLE_STEP,
+                controls.right_throttle_input - THROTTLE_RATE * dt,
                 THROTTLE_MIN,
                 THROTTLE_MAX,
             );
         }
 
         // --- Engine toggles ---
-        // if keyboard.just_pressed(KeyCode::Digit1) {
-        //     controls.left_engine_switch = !controls.left_engine_switch;
-        // }
-        // if keyboard.just_pressed(KeyCode::Digit2) {
-        //     controls.right_engine_switch = !controls.right_engine_switch;
-        // }
+        if keyboard.just_pressed(KeyCode::Digit1) {
+            controls.left_engine_switch = !controls.left_engine_switch;
+        }
+        if keyboard.just_pressed(KeyCode::Digit2) {
+            controls.right_engine_switch = !controls.right_engine_switch;
+        }
 
         // --- Airbrake ---
         if keyboard.just_pressed(KeyCode::KeyB) {
@@ -173,6 +187,16 @@ impl Plane {
             controls.gear_switch = !controls.gear_switch;
         }
 
+        // --- Tailhook ---
+        if keyboard.just_pressed(KeyCode::KeyH) {
+            controls.hook_down = !controls.hook_down;
+        }
+
+        // --- Catapult ---
+        if keyboard.just_pressed(KeyCode::KeyL) {
+            controls.catapult_request = true;
+        }
+
         // --- Wheel brakes ---
         if keyboard.pressed(KeyCode::KeyT) {
             controls.wheel_brake = 1.0;
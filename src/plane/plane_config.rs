@@ -1,15 +1,18 @@
 #![allow(dead_code)]
 #![allow(non_snake_case)]
 
+use crate::plane::integrator::Integrator;
+use crate::plane::table::Table;
 use bevy::math::Vec3;
 use serde::Deserialize;
+use std::fmt;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Unit {
     name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Basic {
     pub wing_area: f32,
     pub wingspan: f32,
@@ -23,7 +26,7 @@ pub struct Basic {
     pub number_of_engines: u8,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Aerodynamics {
     pub Cy0: f32,
     pub Czbe: f32,
@@ -31,41 +34,139 @@ pub struct Aerodynamics {
     pub cx_brk: f32,
     pub cx_flap: f32,
     pub cy_flap: f32,
+    /// Mach number where the flow over the wing first goes supersonic
+    pub mach_critical: f32,
+    /// Drag coefficient the shocks add by `basic.mach_max`. Zero when left out, as in
+    /// configs whose `cx0` table already carries the drag rise.
+    #[serde(default)]
+    pub wave_drag: f32,
+    /// Aft shift of the wing lift at Mach 1, in meters
+    #[serde(default)]
+    pub mach_tuck: f32,
     pub tables: AeroTables,
+    pub derivatives: Derivatives,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct AeroTables {
     pub mach: Vec<f32>,
     pub cx0: Vec<f32>,
     pub Cya: Vec<f32>,
-    pub OmxMax: Vec<f32>,
     pub Aldop: Vec<f32>,
     pub CyMax: Vec<f32>,
+    /// Optional lift coefficient against alpha in degrees and Mach. Replaces the linear
+    /// lift curve, `Cya` and `CyMax` still set the alpha where the wing stalls.
+    pub Cy: Option<Table>,
+    /// Optional drag coefficient against alpha in degrees and Mach. Replaces `cx0` and
+    /// the wave drag.
+    pub Cx: Option<Table>,
+    /// Optional pitching moment coefficient against alpha in degrees and Mach. Replaces
+    /// `Cm0` and `Cm_alpha`.
+    pub Cm: Option<Table>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Stability and control derivatives, per radian. Moments are referenced to the wing area
+/// and the span for roll and yaw, the mean chord for pitch. Rates are made dimensionless
+/// with the same lengths over twice the airspeed. Axes are forward, right and down.
+#[derive(Debug, Default, Deserialize)]
+pub struct Derivatives {
+    pub Cm0: f32,
+    pub Cm_alpha: f32,
+    pub Cm_q: f32,
+    pub Cm_de: f32,
+    pub Cl_beta: f32,
+    pub Cl_p: f32,
+    pub Cl_r: f32,
+    pub Cl_da: f32,
+    pub Cl_dr: f32,
+    pub Cn_beta: f32,
+    pub Cn_p: f32,
+    pub Cn_r: f32,
+    pub Cn_da: f32,
+    pub Cn_dr: f32,
+    /// Control surface travel, in degrees
+    pub elevator_up: f32,
+    pub elevator_down: f32,
+    pub aileron_travel: f32,
+    pub rudder_travel: f32,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Engine {
     pub idle_rpm: f32,
     pub fuel_consumption: f32,
     pub engine_start_time: f32,
+    pub spool_up_time: f32,
+    pub spool_down_time: f32,
+    pub flameout_pressure: f32,
+    pub relight_pressure: f32,
     pub tables: EngineTables,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct EngineTables {
     pub mach: Vec<f32>,
     pub max_thrust: Vec<f32>,
     pub throttle_input: Vec<f32>,
     pub engine_power: Vec<f32>,
     pub engine_power_readout: Vec<f32>,
+    /// Optional max thrust against altitude and Mach. Replaces the lapse model.
+    pub max_thrust_altitude: Option<Table>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Fuel {
+    pub tanks: Vec<FuelTank>,
 }
 
-#[derive(Debug, Deserialize, Copy, Clone)]
+#[derive(Debug, Default, Deserialize)]
+pub struct FuelTank {
+    pub name: String,
+    pub capacity: f32,
+    pub initial: f32,
+    #[serde(default)]
+    pub external: bool,
+    #[serde(default)]
+    pub priority: u8,
+}
+
+#[derive(Debug, Default, Deserialize, Copy, Clone)]
+pub struct Strut {
+    /// Spring rate, N/m
+    pub stiffness: f32,
+    /// Damper rate, N per m/s of compression speed
+    pub damping: f32,
+    /// Compression before the strut bottoms out, m
+    pub travel: f32,
+    pub wheel_radius: f32,
+    /// Strut load that collapses the leg, N
+    pub max_load: f32,
+}
+
+#[derive(Debug, Default, Deserialize, Copy, Clone)]
 pub struct Structure {
     pub front_wheel: Vec3,
     pub back_left_wheel: Vec3,
     pub back_right_wheel: Vec3,
+    pub front_strut: Strut,
+    pub back_left_strut: Strut,
+    pub back_right_strut: Strut,
+    /// Airspeed above which an extended or extending gear fails, m/s
+    pub gear_limit_speed: f32,
+    /// Fuselage points the aircraft rests on with the gear up: front, rear left, rear right
+    pub belly_contacts: [Vec3; 3],
+    pub nose_contact: Vec3,
+    pub tail_contact: Vec3,
+    pub left_wingtip_contact: Vec3,
+    pub right_wingtip_contact: Vec3,
+    pub left_engine_contact: Vec3,
+    pub right_engine_contact: Vec3,
+    /// Tip of the lowered tailhook
+    pub hook_point: Vec3,
+    /// Sink speed of an airframe contact that destroys the aircraft, m/s
+    pub crash_sink_speed: f32,
+    /// Tilt away from upright, in degrees, past which any airframe contact is a crash
+    pub crash_tilt: f32,
     pub left_wing_pos: Vec3,
     pub right_wing_pos: Vec3,
     pub tail_pos: Vec3,
@@ -77,31 +178,414 @@ pub struct Structure {
     pub right_engine_pos: Vec3,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct Simulation {
+    #[serde(default)]
+    pub integrator: Integrator,
+}
+
 /// Main configuration struct
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct PlaneConfig {
     pub unit: Unit,
     pub basic: Basic,
     pub aerodynamics: Aerodynamics,
     pub engine: Engine,
+    pub fuel: Fuel,
     pub structure: Structure,
+    #[serde(default)]
+    pub simulation: Simulation,
 }
 
-pub fn load_config(plane_type: &str) -> PlaneConfig {
-    let config_path = format!("assets/aircraft/{}/config.toml", plane_type);
-    let config_str = std::fs::read_to_string(config_path).expect("Failed to read config file");
-    let config: PlaneConfig = toml::from_str(&config_str).expect("Failed to parse config file");
-    config
+/// Why an aircraft config could not be loaded
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    Parse {
+        path: String,
+        source: toml::de::Error,
+    },
+    /// The file parsed but holds values the flight model cannot use
+    Invalid {
+        path: String,
+        problems: Vec<ConfigProblem>,
+    },
+    /// Written for an older config format, without keys the flight model now needs
+    Outdated {
+        path: String,
+        problems: Vec<ConfigProblem>,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => write!(f, "{path}: {source}"),
+            ConfigError::Parse { path, source } => write!(f, "{path}: {source}"),
+            ConfigError::Invalid { path, problems } => {
+                write!(f, "{path}: {} invalid values", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {problem}")?;
+                }
+                Ok(())
+            }
+            ConfigError::Outdated { path, problems } => {
+                write!(
+                    f,
+                    "{path}: written for an older config format, {} keys missing. \
+                     See {EXAMPLE_CONFIG} for an example of each.",
+                    problems.len()
+                )?;
+                for problem in problems {
+                    write!(f, "\n  {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+            ConfigError::Invalid { .. } | ConfigError::Outdated { .. } => None,
+        }
+    }
+}
+
+/// One bad value, with the TOML key path it was read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+const EXAMPLE_CONFIG: &str = "assets/aircraft/su-25t/config.toml";
+
+/// Required keys added to the format after the first aircraft configs were written. Newer
+/// optional keys have serde defaults instead.
+const ADDED_KEYS: [&str; 21] = [
+    "aerodynamics.mach_critical",
+    "aerodynamics.derivatives",
+    "engine.spool_up_time",
+    "engine.spool_down_time",
+    "engine.flameout_pressure",
+    "engine.relight_pressure",
+    "fuel.tanks",
+    "structure.front_strut",
+    "structure.back_left_strut",
+    "structure.back_right_strut",
+    "structure.gear_limit_speed",
+    "structure.belly_contacts",
+    "structure.nose_contact",
+    "structure.tail_contact",
+    "structure.left_wingtip_contact",
+    "structure.right_wingtip_contact",
+    "structure.left_engine_contact",
+    "structure.right_engine_contact",
+    "structure.hook_point",
+    "structure.crash_sink_speed",
+    "structure.crash_tilt",
+];
+
+/// `ADDED_KEYS` missing from a config, so an old config gets one list of everything to add
+/// instead of serde's first missing field
+fn missing_added_keys(config_str: &str) -> Vec<ConfigProblem> {
+    let Ok(table) = config_str.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let has_key = |key: &str| {
+        let mut parts = key.split('.');
+        let mut value = parts.next().and_then(|part| table.get(part));
+        for part in parts {
+            value = value.and_then(|value| value.get(part));
+        }
+        value.is_some()
+    };
+    ADDED_KEYS
+        .iter()
+        .filter(|key| !has_key(key))
+        .map(|key| ConfigProblem {
+            key: key.to_string(),
+            message: "missing".to_string(),
+        })
+        .collect()
+}
+
+pub fn load_config(plane_type: &str) -> Result<PlaneConfig, ConfigError> {
+    let path = format!("assets/aircraft/{}/config.toml", plane_type);
+    let config_str = match std::fs::read_to_string(&path) {
+        Ok(config_str) => config_str,
+        Err(source) => return Err(ConfigError::Read { path, source }),
+    };
+    let config: PlaneConfig = match toml::from_str(&config_str) {
+        Ok(config) => config,
+        Err(source) => {
+            let problems = missing_added_keys(&config_str);
+            return Err(if problems.is_empty() {
+                ConfigError::Parse { path, source }
+            } else {
+                ConfigError::Outdated { path, problems }
+            });
+        }
+    };
+    match config.validate() {
+        Ok(()) => Ok(config),
+        Err(problems) => Err(ConfigError::Invalid { path, problems }),
+    }
 }
 
 impl PlaneConfig {
-    pub fn new(plane_type: &str) -> Self {
-        load_config(plane_type)
+    /// Checks the values serde cannot, reporting every problem found rather than the first
+    pub fn validate(&self) -> Result<(), Vec<ConfigProblem>> {
+        let mut check = Checks::default();
+
+        let basic = &self.basic;
+        check.positive("basic.wing_area", basic.wing_area);
+        check.positive("basic.wingspan", basic.wingspan);
+        check.positive("basic.length", basic.length);
+        check.positive("basic.mach_max", basic.mach_max);
+        check.positive("basic.empty_mass", basic.empty_mass);
+        check.positive("basic.gross_mass", basic.gross_mass);
+        for (i, name) in ["Ix", "Iy", "Iz"].iter().enumerate() {
+            check.positive(
+                &format!("basic.moment_of_inertia[{i}] ({name})"),
+                basic.moment_of_inertia[i],
+            );
+        }
+        // The roll-yaw block of the tensor has to stay positive definite, or it can't be
+        // inverted to turn moments into angular accelerations
+        let [_, iy, iz, ixy] = basic.moment_of_inertia;
+        if ixy.is_nan() || ixy * ixy >= iy * iz {
+            check.problem(
+                "basic.moment_of_inertia[3] (Ixy)",
+                format!(
+                    "Ixy squared must be less than Iy * Iz ({}), found {ixy}",
+                    iy * iz
+                ),
+            );
+        }
+
+        let aero = &self.aerodynamics;
+        if aero.mach_critical.is_nan() || aero.mach_critical <= 0.0 || aero.mach_critical >= 1.0 {
+            check.problem(
+                "aerodynamics.mach_critical",
+                format!("must be between 0 and 1, found {}", aero.mach_critical),
+            );
+        }
+        if aero.mach_critical >= self.basic.mach_max {
+            check.problem(
+                "aerodynamics.mach_critical",
+                format!(
+                    "must be below basic.mach_max ({}), found {}",
+                    self.basic.mach_max, aero.mach_critical
+                ),
+            );
+        }
+        check.not_negative("aerodynamics.wave_drag", aero.wave_drag);
+        check.not_negative("aerodynamics.mach_tuck", aero.mach_tuck);
+
+        let at = &aero.tables;
+        check.breakpoints("aerodynamics.tables.mach", &at.mach);
+        for (key, values) in [
+            ("cx0", &at.cx0),
+            ("Cya", &at.Cya),
+            ("Aldop", &at.Aldop),
+            ("CyMax", &at.CyMax),
+        ] {
+            check.same_length(
+                &format!("aerodynamics.tables.{key}"),
+                values,
+                "aerodynamics.tables.mach",
+                &at.mach,
+            );
+        }
+        for (key, table) in [("Cy", &at.Cy), ("Cx", &at.Cx), ("Cm", &at.Cm)] {
+            if let Some(table) = table {
+                check.table(
+                    &format!("aerodynamics.tables.{key}"),
+                    table,
+                    &["alpha", "mach"],
+                );
+            }
+        }
+
+        let d = &aero.derivatives;
+        for (key, travel) in [
+            ("elevator_up", d.elevator_up),
+            ("elevator_down", d.elevator_down),
+            ("aileron_travel", d.aileron_travel),
+            ("rudder_travel", d.rudder_travel),
+        ] {
+            check.not_negative(&format!("aerodynamics.derivatives.{key}"), travel);
+        }
+
+        let engine = &self.engine;
+        check.not_negative("engine.fuel_consumption", engine.fuel_consumption);
+        if engine.idle_rpm.is_nan() || engine.idle_rpm <= 0.0 || engine.idle_rpm >= 100.0 {
+            check.problem(
+                "engine.idle_rpm",
+                format!("must be between 0 and 100, found {}", engine.idle_rpm),
+            );
+        }
+        check.positive("engine.engine_start_time", engine.engine_start_time);
+        check.positive("engine.spool_up_time", engine.spool_up_time);
+        check.positive("engine.spool_down_time", engine.spool_down_time);
+
+        let et = &engine.tables;
+        check.breakpoints("engine.tables.mach", &et.mach);
+        check.same_length(
+            "engine.tables.max_thrust",
+            &et.max_thrust,
+            "engine.tables.mach",
+            &et.mach,
+        );
+        check.breakpoints("engine.tables.throttle_input", &et.throttle_input);
+        check.breakpoints(
+            "engine.tables.engine_power_readout",
+            &et.engine_power_readout,
+        );
+        for (key, values) in [
+            ("engine_power", &et.engine_power),
+            ("engine_power_readout", &et.engine_power_readout),
+        ] {
+            check.same_length(
+                &format!("engine.tables.{key}"),
+                values,
+                "engine.tables.throttle_input",
+                &et.throttle_input,
+            );
+        }
+        if let Some(table) = &et.max_thrust_altitude {
+            check.table(
+                "engine.tables.max_thrust_altitude",
+                table,
+                &["altitude", "mach"],
+            );
+        }
+
+        for (i, tank) in self.fuel.tanks.iter().enumerate() {
+            let key = format!("fuel.tanks[{i}] ({})", tank.name);
+            check.not_negative(&format!("{key}.capacity"), tank.capacity);
+            if !(0.0..=tank.capacity).contains(&tank.initial) {
+                check.problem(
+                    &format!("{key}.initial"),
+                    format!("must be between 0 and the capacity, found {}", tank.initial),
+                );
+            }
+        }
+
+        let structure = &self.structure;
+        check.positive("structure.gear_limit_speed", structure.gear_limit_speed);
+        check.positive("structure.crash_sink_speed", structure.crash_sink_speed);
+        check.positive("structure.crash_tilt", structure.crash_tilt);
+        for (key, strut) in [
+            ("front_strut", &structure.front_strut),
+            ("back_left_strut", &structure.back_left_strut),
+            ("back_right_strut", &structure.back_right_strut),
+        ] {
+            check.positive(&format!("structure.{key}.stiffness"), strut.stiffness);
+            check.not_negative(&format!("structure.{key}.damping"), strut.damping);
+            check.positive(&format!("structure.{key}.travel"), strut.travel);
+            check.not_negative(&format!("structure.{key}.wheel_radius"), strut.wheel_radius);
+            check.positive(&format!("structure.{key}.max_load"), strut.max_load);
+        }
+
+        if check.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(check.problems)
+        }
     }
 }
 
-impl Default for PlaneConfig {
-    fn default() -> Self {
-        PlaneConfig::new("su-25t")
+#[derive(Default)]
+struct Checks {
+    problems: Vec<ConfigProblem>,
+}
+
+impl Checks {
+    fn problem(&mut self, key: &str, message: String) {
+        self.problems.push(ConfigProblem {
+            key: key.to_string(),
+            message,
+        });
+    }
+
+    fn positive(&mut self, key: &str, value: f32) {
+        if value.is_nan() || value <= 0.0 {
+            self.problem(key, format!("must be positive, found {value}"));
+        }
+    }
+
+    fn not_negative(&mut self, key: &str, value: f32) {
+        if value.is_nan() || value < 0.0 {
+            self.problem(key, format!("must not be negative, found {value}"));
+        }
+    }
+
+    /// Lookups need at least one breakpoint and a strictly increasing axis
+    fn breakpoints(&mut self, key: &str, xs: &[f32]) {
+        if xs.is_empty() {
+            self.problem(key, "needs at least one breakpoint".to_string());
+        }
+        if let Some(i) = xs
+            .windows(2)
+            .position(|pair| pair[0].is_nan() || pair[1].is_nan() || pair[0] >= pair[1])
+        {
+            self.problem(
+                key,
+                format!(
+                    "breakpoints must increase, found {} then {} at index {}",
+                    xs[i],
+                    xs[i + 1],
+                    i + 1
+                ),
+            );
+        }
+    }
+
+    fn same_length(&mut self, key: &str, values: &[f32], axis_key: &str, axis: &[f32]) {
+        if values.len() != axis.len() {
+            self.problem(
+                key,
+                format!(
+                    "has {} values but {axis_key} has {}",
+                    values.len(),
+                    axis.len()
+                ),
+            );
+        }
+    }
+
+    /// `lookup` takes one coordinate per axis and only checks the count in debug builds,
+    /// so every table has to have exactly the axes it is looked up with
+    fn table(&mut self, key: &str, table: &Table, axis_names: &[&str]) {
+        let axes = table.axes();
+        if axes.len() != axis_names.len() {
+            self.problem(
+                key,
+                format!(
+                    "needs {} axes ({}), found {}",
+                    axis_names.len(),
+                    axis_names.join(", "),
+                    axes.len()
+                ),
+            );
+        }
+        for (i, axis) in axes.iter().enumerate() {
+            self.breakpoints(&format!("{key}.axes[{i}]"), axis);
+        }
     }
 }
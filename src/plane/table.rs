@@ -0,0 +1,294 @@
+use crate::util::locate;
+use serde::Deserialize;
+
+// Lookups blend the corners of one cell on the stack, 2^MAX_AXES of them
+const MAX_AXES: usize = 4;
+
+/// What a lookup returns past the first or last breakpoint of an axis
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Extrapolation {
+    /// Holds the value at the edge
+    #[default]
+    Clamp,
+    /// Carries on along the slope of the outermost interval
+    Linear,
+}
+
+/// Gridded data over one to four axes, interpolated linearly along each of them.
+///
+/// In TOML the values nest one array level per axis, the first axis outermost:
+/// `{ axes = [[alpha...], [mach...]], values = [[...], [...]], extrapolation = "clamp" }`
+/// holds one row of Mach values per alpha breakpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawTable")]
+pub struct Table {
+    axes: Vec<Vec<f32>>,
+    /// Row-major, the last axis varies fastest
+    values: Vec<f32>,
+    extrapolation: Extrapolation,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawTable {
+    axes: Vec<Vec<f32>>,
+    values: Values,
+    #[serde(default)]
+    extrapolation: Extrapolation,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Values {
+    Row(Vec<f32>),
+    Nested(Vec<Values>),
+}
+
+impl TryFrom<RawTable> for Table {
+    type Error = String;
+
+    fn try_from(raw: RawTable) -> Result<Self, Self::Error> {
+        if raw.axes.is_empty() || raw.axes.len() > MAX_AXES {
+            return Err(format!(
+                "a table needs 1 to {MAX_AXES} axes, found {}",
+                raw.axes.len()
+            ));
+        }
+        if let Some(axis) = raw.axes.iter().position(|axis| axis.is_empty()) {
+            return Err(format!("axis {axis} has no breakpoints"));
+        }
+
+        let mut values = Vec::with_capacity(raw.axes.iter().map(Vec::len).product());
+        flatten(&raw.values, &raw.axes, 0, &mut values)?;
+
+        Ok(Table {
+            axes: raw.axes,
+            values,
+            extrapolation: raw.extrapolation,
+        })
+    }
+}
+
+/// Appends the nested `values` for axis `level` onwards in row-major order, checking they
+/// match the axis lengths
+fn flatten(
+    values: &Values,
+    axes: &[Vec<f32>],
+    level: usize,
+    out: &mut Vec<f32>,
+) -> Result<(), String> {
+    let last = level + 1 == axes.len();
+    let expected = axes[level].len();
+    let found = match values {
+        Values::Row(row) if last => row.len(),
+        Values::Nested(rows) if !last => rows.len(),
+        _ => {
+            return Err(format!(
+                "values nest one array per axis, expected {} levels",
+                axes.len()
+            ));
+        }
+    };
+    if found != expected {
+        return Err(format!(
+            "axis {level} has {expected} breakpoints but the values have {found} entries along it"
+        ));
+    }
+
+    match values {
+        Values::Row(row) => out.extend_from_slice(row),
+        Values::Nested(rows) => {
+            for row in rows {
+                flatten(row, axes, level + 1, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Table {
+    /// Breakpoints of each axis, outermost first
+    pub fn axes(&self) -> &[Vec<f32>] {
+        &self.axes
+    }
+
+    /// Interpolated value at `point`, one coordinate per axis
+    pub fn lookup(&self, point: &[f32]) -> f32 {
+        debug_assert_eq!(point.len(), self.axes.len());
+        let extrapolate = self.extrapolation == Extrapolation::Linear;
+
+        let mut cell = [(0, 0.0); MAX_AXES];
+        for (slot, (axis, &x)) in cell.iter_mut().zip(self.axes.iter().zip(point)) {
+            *slot = locate(axis, x, extrapolate);
+        }
+
+        // Blend the corners of the cell holding the point, bit `a` of `corner` picks the
+        // upper breakpoint along axis `a`
+        let mut value = 0.0;
+        for corner in 0..(1usize << self.axes.len()) {
+            let mut index = 0;
+            let mut weight = 1.0;
+            for (a, (axis, &(lower, t))) in self.axes.iter().zip(&cell).enumerate() {
+                let upper = corner & (1 << a) != 0;
+                index = index * axis.len() + (lower + upper as usize).min(axis.len() - 1);
+                weight *= if upper { t } else { 1.0 - t };
+            }
+            value += weight * self.values[index];
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Wrapper {
+        table: Table,
+    }
+
+    /// Parses `source` as the inline TOML table behind a `table = ` key
+    fn parse(source: &str) -> Result<Table, String> {
+        toml::from_str::<Wrapper>(&format!("table = {source}"))
+            .map(|wrapper| wrapper.table)
+            .map_err(|error| error.to_string())
+    }
+
+    fn table(source: &str) -> Table {
+        parse(source).unwrap()
+    }
+
+    fn assert_lookup(table: &Table, point: &[f32], expected: f32) {
+        let value = table.lookup(point);
+        assert!(
+            (value - expected).abs() < 1e-5,
+            "at {point:?} expected {expected}, found {value}"
+        );
+    }
+
+    #[test]
+    fn one_axis() {
+        let table = table("{ axes = [[0.0, 1.0, 3.0]], values = [10.0, 20.0, 0.0] }");
+        assert_eq!(table.axes(), &[vec![0.0, 1.0, 3.0]]);
+        assert_lookup(&table, &[0.0], 10.0);
+        assert_lookup(&table, &[1.0], 20.0);
+        assert_lookup(&table, &[3.0], 0.0);
+        assert_lookup(&table, &[0.5], 15.0);
+        assert_lookup(&table, &[2.0], 10.0);
+    }
+
+    #[test]
+    fn two_axes() {
+        // value = 10 * x + y
+        let table = table(
+            "{ axes = [[0.0, 1.0], [0.0, 2.0, 4.0]], \
+               values = [[0.0, 2.0, 4.0], [10.0, 12.0, 14.0]] }",
+        );
+        assert_lookup(&table, &[0.0, 0.0], 0.0);
+        assert_lookup(&table, &[1.0, 2.0], 12.0);
+        assert_lookup(&table, &[0.0, 4.0], 4.0);
+        assert_lookup(&table, &[0.5, 1.0], 6.0);
+        assert_lookup(&table, &[0.25, 3.0], 5.5);
+    }
+
+    #[test]
+    fn three_axes() {
+        // value = 100 * x + 10 * y + z
+        let table = table(
+            "{ axes = [[0.0, 1.0], [0.0, 1.0], [0.0, 1.0]], values = [\
+               [[0.0, 1.0], [10.0, 11.0]], \
+               [[100.0, 101.0], [110.0, 111.0]]] }",
+        );
+        assert_lookup(&table, &[0.0, 0.0, 0.0], 0.0);
+        assert_lookup(&table, &[1.0, 0.0, 1.0], 101.0);
+        assert_lookup(&table, &[1.0, 1.0, 1.0], 111.0);
+        assert_lookup(&table, &[0.5, 0.5, 0.5], 55.5);
+        assert_lookup(&table, &[0.25, 1.0, 0.75], 35.75);
+    }
+
+    #[test]
+    fn single_breakpoint() {
+        let table = table("{ axes = [[2.0]], values = [7.0] }");
+        assert_lookup(&table, &[-5.0], 7.0);
+        assert_lookup(&table, &[2.0], 7.0);
+        assert_lookup(&table, &[9.0], 7.0);
+    }
+
+    #[test]
+    fn clamp_holds_the_edges() {
+        let source = "{ axes = [[0.0, 1.0], [0.0, 2.0]], values = [[0.0, 2.0], [10.0, 12.0]] }";
+        let table = table(source);
+        assert_eq!(table.extrapolation, Extrapolation::Clamp);
+        assert_lookup(&table, &[-1.0, 1.0], 1.0);
+        assert_lookup(&table, &[2.0, 1.0], 11.0);
+        assert_lookup(&table, &[0.5, -3.0], 5.0);
+        assert_lookup(&table, &[0.5, 6.0], 7.0);
+        assert_lookup(&table, &[5.0, 5.0], 12.0);
+
+        let explicit = parse(&source.replace(" }", ", extrapolation = \"clamp\" }")).unwrap();
+        assert_eq!(explicit.extrapolation, Extrapolation::Clamp);
+        assert_lookup(&explicit, &[-1.0, -1.0], 0.0);
+    }
+
+    #[test]
+    fn linear_follows_the_outer_slope() {
+        // value = 10 * x + y
+        let table = table(
+            "{ axes = [[0.0, 1.0, 2.0], [0.0, 2.0]], \
+               values = [[0.0, 2.0], [10.0, 12.0], [20.0, 22.0]], \
+               extrapolation = \"linear\" }",
+        );
+        assert_eq!(table.extrapolation, Extrapolation::Linear);
+        assert_lookup(&table, &[-1.0, 1.0], -9.0);
+        assert_lookup(&table, &[3.0, 1.0], 31.0);
+        assert_lookup(&table, &[1.0, -2.0], 8.0);
+        assert_lookup(&table, &[1.0, 4.0], 14.0);
+        assert_lookup(&table, &[-1.0, 4.0], -6.0);
+        assert_lookup(&table, &[1.5, 1.0], 16.0);
+    }
+
+    #[test]
+    fn rejects_unknown_extrapolation() {
+        assert!(
+            parse("{ axes = [[0.0, 1.0]], values = [0.0, 1.0], extrapolation = \"cubic\" }")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_lengths() {
+        let error = parse("{ axes = [[0.0, 1.0, 2.0]], values = [0.0, 1.0] }").unwrap_err();
+        assert!(error.contains("axis 0 has 3 breakpoints"), "{error}");
+
+        let error =
+            parse("{ axes = [[0.0, 1.0], [0.0, 1.0]], values = [[0.0, 1.0], [2.0]] }").unwrap_err();
+        assert!(error.contains("axis 1 has 2 breakpoints"), "{error}");
+
+        let error =
+            parse("{ axes = [[0.0, 1.0], [0.0, 1.0]], values = [[0.0, 1.0]] }").unwrap_err();
+        assert!(error.contains("axis 0 has 2 breakpoints"), "{error}");
+    }
+
+    #[test]
+    fn rejects_wrong_nesting() {
+        let error = parse("{ axes = [[0.0, 1.0], [0.0, 1.0]], values = [0.0, 1.0] }").unwrap_err();
+        assert!(error.contains("expected 2 levels"), "{error}");
+
+        let error = parse("{ axes = [[0.0, 1.0]], values = [[0.0], [1.0]] }").unwrap_err();
+        assert!(error.contains("expected 1 levels"), "{error}");
+    }
+
+    #[test]
+    fn rejects_bad_axes() {
+        let error = parse("{ axes = [], values = [] }").unwrap_err();
+        assert!(error.contains("1 to 4 axes"), "{error}");
+
+        let axes = ["[0.0]"; MAX_AXES + 1].join(", ");
+        let error = parse(&format!("{{ axes = [{axes}], values = [[[[[0.0]]]]] }}")).unwrap_err();
+        assert!(error.contains("1 to 4 axes"), "{error}");
+
+        let error = parse("{ axes = [[0.0, 1.0], []], values = [[], []] }").unwrap_err();
+        assert!(error.contains("axis 1 has no breakpoints"), "{error}");
+    }
+}
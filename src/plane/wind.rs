@@ -0,0 +1,240 @@
+use crate::plane::atmosphere::Atmosphere;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+const WEATHER_CONFIG: &str = "assets/weather/config.toml";
+const FEET_PER_M: f32 = 3.28084;
+const MIN_TURBULENCE_AIRSPEED: f32 = 10.0; // m/s
+
+/// Discrete "1 - cosine" gust
+#[derive(Debug, Copy, Clone)]
+pub struct Gust {
+    pub start: f32,     // Sim time the gust begins, s
+    pub duration: f32,  // s
+    pub velocity: Vec3, // Peak gust velocity, world frame, m/s
+}
+
+impl Gust {
+    pub fn velocity_at(&self, time: f32) -> Vec3 {
+        let t = time - self.start;
+        if t <= 0.0 || t >= self.duration {
+            return Vec3::ZERO;
+        }
+        self.velocity * 0.5 * (1.0 - (2.0 * PI * t / self.duration).cos())
+    }
+}
+
+/// Air mass motion shared by every aircraft
+#[derive(Resource, Debug, Clone)]
+pub struct WindField {
+    /// Steady wind at `reference_altitude`, world frame, m/s (direction the air moves)
+    pub surface_wind: Vec3,
+    pub reference_altitude: f32, // m AGL
+    /// Power law exponent of the boundary layer profile, 1/7 over open terrain
+    pub shear_exponent: f32,
+    /// Above this altitude the steady wind stops growing, m
+    pub gradient_altitude: f32,
+    /// Extra wind per metre of altitude above the boundary layer, m/s per m
+    pub upper_shear: Vec3,
+    pub gusts: Vec<Gust>,
+    /// Vertical turbulence intensity (sigma_w), m/s. 0 disables turbulence.
+    pub turbulence_intensity: f32,
+}
+
+impl Default for WindField {
+    fn default() -> Self {
+        Self {
+            surface_wind: Vec3::ZERO,
+            reference_altitude: 10.0,
+            shear_exponent: 1.0 / 7.0,
+            gradient_altitude: 600.0,
+            upper_shear: Vec3::ZERO,
+            gusts: Vec::new(),
+            turbulence_intensity: 0.0,
+        }
+    }
+}
+
+/// Wind, turbulence and air mass as set in the weather config. Directions are where the wind
+/// blows from, in degrees true.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WeatherConfig {
+    /// Steady wind at `reference_altitude`, m/s
+    pub wind_speed: f32,
+    pub wind_direction: f32,
+    pub reference_altitude: f32,
+    pub shear_exponent: f32,
+    pub gradient_altitude: f32,
+    /// Wind gained per metre above `gradient_altitude`, m/s per m, from `wind_direction`
+    pub upper_shear: f32,
+    /// Vertical turbulence intensity (sigma_w), m/s
+    pub turbulence_intensity: f32,
+    pub gusts: Vec<GustConfig>,
+    /// Deviation from the standard temperature at every altitude, K
+    pub temperature_offset: f32,
+    /// Deviation from the standard sea-level pressure, Pa
+    pub pressure_offset: f32,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        let calm = WindField::default();
+        Self {
+            wind_speed: 0.0,
+            wind_direction: 0.0,
+            reference_altitude: calm.reference_altitude,
+            shear_exponent: calm.shear_exponent,
+            gradient_altitude: calm.gradient_altitude,
+            upper_shear: 0.0,
+            turbulence_intensity: calm.turbulence_intensity,
+            gusts: Vec::new(),
+            temperature_offset: 0.0,
+            pressure_offset: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GustConfig {
+    pub start: f32,
+    pub duration: f32,
+    /// Peak horizontal gust speed, m/s
+    pub speed: f32,
+    pub direction: f32,
+    /// Peak vertical gust speed, m/s, up positive
+    #[serde(default)]
+    pub vertical: f32,
+}
+
+/// World velocity of air blowing from `direction` degrees true at `speed`
+fn blowing_from(direction: f32, speed: f32) -> Vec3 {
+    Quat::from_rotation_y(-direction.to_radians()) * Vec3::Z * speed
+}
+
+impl From<WeatherConfig> for WindField {
+    fn from(config: WeatherConfig) -> Self {
+        Self {
+            surface_wind: blowing_from(config.wind_direction, config.wind_speed),
+            reference_altitude: config.reference_altitude,
+            shear_exponent: config.shear_exponent,
+            gradient_altitude: config.gradient_altitude,
+            upper_shear: blowing_from(config.wind_direction, config.upper_shear),
+            gusts: config
+                .gusts
+                .iter()
+                .map(|gust| Gust {
+                    start: gust.start,
+                    duration: gust.duration,
+                    velocity: blowing_from(gust.direction, gust.speed) + Vec3::Y * gust.vertical,
+                })
+                .collect(),
+            turbulence_intensity: config.turbulence_intensity,
+        }
+    }
+}
+
+pub fn load_weather() -> Result<WeatherConfig, String> {
+    let config_str = std::fs::read_to_string(WEATHER_CONFIG)
+        .map_err(|err| format!("{WEATHER_CONFIG}: {err}"))?;
+    toml::from_str(&config_str).map_err(|err| format!("{WEATHER_CONFIG}: {err}"))
+}
+
+pub fn setup_weather(mut commands: Commands) {
+    match load_weather() {
+        Ok(config) => {
+            commands.insert_resource(Atmosphere {
+                temperature_offset: config.temperature_offset,
+                pressure_offset: config.pressure_offset,
+            });
+            commands.insert_resource(WindField::from(config));
+        }
+        Err(err) => warn!("No weather, using calm air in a standard atmosphere: {err}"),
+    }
+}
+
+impl WindField {
+    /// Steady wind plus any active gusts at `altitude` metres and sim `time`
+    pub fn wind_at(&self, altitude: f32, time: f32) -> Vec3 {
+        let h = altitude.max(0.0);
+        let boundary = h.min(self.gradient_altitude);
+        let profile = (boundary.max(0.1) / self.reference_altitude).powf(self.shear_exponent);
+        let above = (h - self.gradient_altitude).max(0.0);
+
+        let gusts: Vec3 = self.gusts.iter().map(|g| g.velocity_at(time)).sum();
+        self.surface_wind * profile + self.upper_shear * above + gusts
+    }
+}
+
+/// Dryden turbulence filter state for one aircraft (MIL-F-8785C)
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Turbulence {
+    u: f32,
+    v: [f32; 2],
+    w: [f32; 2],
+    /// Body frame gust velocity: x right, y up, z aft, m/s
+    pub velocity: Vec3,
+}
+
+impl Turbulence {
+    /// Advances the shaping filters by `dt` for the given airspeed and height.
+    pub fn update(&mut self, dt: f32, airspeed: f32, altitude_agl: f32, sigma_w: f32) {
+        if sigma_w <= 0.0 {
+            *self = Self::default();
+            return;
+        }
+
+        let v_air = airspeed.max(MIN_TURBULENCE_AIRSPEED);
+        let (l_uv, l_w, sigma_uv) = dryden_scales(altitude_agl, sigma_w);
+
+        // Longitudinal: sigma * sqrt(2L/(pi V)) / (1 + L/V s)
+        let tau_u = l_uv / v_air;
+        self.u = lag(self.u, white_noise(2.0 * tau_u, dt), tau_u, dt);
+
+        // Lateral and vertical: sigma * sqrt(L/(pi V)) (1 + sqrt(3) L/V s) / (1 + L/V s)^2
+        let tau_v = l_uv / v_air;
+        let tau_w = l_w / v_air;
+        let v = second_order(&mut self.v, white_noise(tau_v, dt), tau_v, dt);
+        let w = second_order(&mut self.w, white_noise(tau_w, dt), tau_w, dt);
+
+        self.velocity = vec3(v * sigma_uv, w * sigma_w, -self.u * sigma_uv);
+    }
+}
+
+/// Horizontal and vertical scale lengths (m) and horizontal intensity (m/s)
+/// of the low altitude Dryden model. Values are held at 1000 ft above that.
+fn dryden_scales(altitude_agl: f32, sigma_w: f32) -> (f32, f32, f32) {
+    let h = (altitude_agl * FEET_PER_M).clamp(10.0, 1000.0);
+    let k = 0.177 + 0.000823 * h;
+
+    let l_w = h / FEET_PER_M;
+    let l_uv = h / k.powf(1.2) / FEET_PER_M;
+    let sigma_uv = sigma_w / k.powf(0.4);
+
+    (l_uv, l_w, sigma_uv)
+}
+
+/// First order lag toward `input`, exact for a held input
+fn lag(state: f32, input: f32, tau: f32, dt: f32) -> f32 {
+    state + (input - state) * (1.0 - (-dt / tau).exp())
+}
+
+/// Unit variance output of (1 + sqrt(3) tau s) / (1 + tau s)^2
+fn second_order(state: &mut [f32; 2], input: f32, tau: f32, dt: f32) -> f32 {
+    state[0] = lag(state[0], input, tau, dt);
+    state[1] = lag(state[1], state[0], tau, dt);
+    state[1] + 3.0_f32.sqrt() * (state[0] - state[1])
+}
+
+/// Band limited white noise sample with two-sided spectral density `psd`
+fn white_noise(psd: f32, dt: f32) -> f32 {
+    gaussian() * (psd / dt).sqrt()
+}
+
+/// Standard normal sample (Box-Muller)
+fn gaussian() -> f32 {
+    let u1 = rand::random::<f32>().max(f32::EPSILON);
+    let u2 = rand::random::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
@@ -1,4 +1,8 @@
+use crate::plane::plane_config::ConfigError;
+use crate::systems::game::SKY_COLOR;
+use crate::world::streaming::VIEW_DISTANCE;
 use crate::*;
+use bevy::ecs::system::SystemParam;
 
 pub fn spawn_plane(
     mut commands: Commands,
@@ -7,7 +11,14 @@ pub fn spawn_plane(
 ) {
     let plane_name = "su-25t";
 
-    let plane = Plane::new(plane_name, PLANE_SPAWN_VEL);
+    let plane = match Plane::new(plane_name, PLANE_SPAWN_VEL) {
+        Ok(plane) => plane,
+        Err(err) => {
+            error!("Cannot load aircraft {plane_name}: {err}");
+            show_load_error(&mut commands, plane_name, &err);
+            return;
+        }
+    };
     let plane_model_handle: Handle<Scene> =
         asset_server.load(format!("aircraft/{}/model.glb#Scene0", plane_name));
     // let plane_mesh_handle: Handle<Mesh> = asset_server.load(format!(
@@ -21,16 +32,26 @@ pub fn spawn_plane(
     //     ..default()
     // });
 
+    let spawn_transform = Transform {
+        translation: PLANE_SPAWN_POS,
+        rotation: Quat::from_rotation_x(10_f32.to_radians()),
+        ..default()
+    };
+
     // Try to get the mesh asset for collider creation
     let player_entity = commands
         .spawn((
-            Transform {
-                translation: PLANE_SPAWN_POS,
-                rotation: Quat::from_rotation_x(10_f32.to_radians()),
-                ..default()
+            spawn_transform,
+            PhysicsTransform::new(spawn_transform),
+            SpawnPoint {
+                plane_type: plane_name.to_string(),
+                position: spawn_transform.translation.as_dvec3(),
+                rotation: spawn_transform.rotation,
+                velocity: PLANE_SPAWN_VEL,
             },
             Visibility::default(),
             PlaneComponent { plane },
+            AbsolutePosition(spawn_transform.translation.as_dvec3()),
             Player,
         ))
         .with_children(|parent| {
@@ -41,6 +62,19 @@ pub fn spawn_plane(
             ));
             parent.spawn((
                 Camera3d::default(),
+                Projection::Perspective(PerspectiveProjection {
+                    far: VIEW_DISTANCE,
+                    ..default()
+                }),
+                // Fade the world out before the streamed chunks end
+                DistanceFog {
+                    color: SKY_COLOR,
+                    falloff: FogFalloff::Linear {
+                        start: VIEW_DISTANCE * 0.5,
+                        end: VIEW_DISTANCE,
+                    },
+                    ..default()
+                },
                 // Atmosphere::EARTH,
                 Transform {
                     rotation: Quat::from_rotation_x(-30_f32.to_radians()),
@@ -70,26 +104,62 @@ pub fn spawn_plane(
     commands.insert_resource(PlayerEntity(player_entity));
 }
 
+/// Puts the config error on screen, with a camera of its own since there is no aircraft
+/// to carry one
+fn show_load_error(commands: &mut Commands, plane_name: &str, err: &ConfigError) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_translation(PLANE_SPAWN_POS),
+    ));
+    commands.spawn((
+        Text::new(format!("Cannot load aircraft {plane_name}\n{err}")),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.0, 0.0)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
 pub fn update_plane_readings(
-    plane_query: Query<(&Transform, &PlaneComponent), With<Player>>, // Only query player
+    score: Res<Score>,
+    geo: Res<GeoReference>,
+    plane_query: Query<(&AbsolutePosition, &PlaneComponent), With<Player>>, // Only query player
     mut text_query: Query<&mut Text, With<PlaneReadingsText>>,
 ) {
-    if let Ok((transform, plane_component)) = plane_query.single() {
+    if let Ok((position, plane_component)) = plane_query.single() {
         let plane = &plane_component.plane;
         let fm = &plane.flight_model;
+        let geo_position = geo.to_geodetic(position.0);
 
         for mut text in &mut text_query {
             let speed_knots = fm.velocity.length() * 1.94384;
-            let altitude_feet = transform.translation.y * 3.28084;
+            let altitude_feet = geo_position.altitude * 3.28084;
             let throttle_in_percent = fm.left_throttle_input * 100.0;
-            let throttle_out_percent = fm.left_engine_power_readout * 100.0;
+            let hook = match (fm.hook_down, fm.hook_caught()) {
+                (_, Some(wire)) => format!("wire {}", wire + 1),
+                (true, None) => "down".to_string(),
+                (false, None) => "up".to_string(),
+            };
 
             text.0 = format!(
                 "
                 Speed: {:.0} kt
                 Alt: {:.0} ft
+                Pos: {:.5}, {:.5}
+                Hdg: {:03.0} T / {:03.0} M
                 Mach: {:.1}
-                Throttle: {:.0}% | {:.0}%
+                Throttle: {:.0}%
+                RPM: {:.0}% | {:.0}%
+                EGT: {:.0} | {:.0} C
+                Engines: {:?} | {:?}
+                Fuel: {:.0} kg
                 A: {:.2}
                 g: {:.2}
                 P,R,Y: {:.2}, {:.2}, {:.2}
@@ -97,12 +167,25 @@ pub fn update_plane_readings(
                 Flaps: {:.2}
                 Airbrakes: {:.2}
                 Slats: {:.2}
+                Hook: {}{}
+                Damage: {:.0}%
+                Kills: {} Crashes: {}
                 ",
                 speed_knots,
                 altitude_feet,
+                geo_position.latitude,
+                geo_position.longitude,
+                fm.true_heading,
+                fm.magnetic_heading,
                 fm.mach,
                 throttle_in_percent,
-                throttle_out_percent,
+                fm.left_engine.rpm * 100.0,
+                fm.right_engine.rpm * 100.0,
+                fm.left_engine.egt,
+                fm.right_engine.egt,
+                fm.left_engine.state,
+                fm.right_engine.state,
+                fm.total_fuel,
                 fm.alpha,
                 fm.g,
                 fm.pitch_input,
@@ -112,27 +195,77 @@ pub fn update_plane_readings(
                 fm.flaps_pos,
                 fm.airbrake_pos,
                 fm.slats_pos,
+                hook,
+                if fm.launching() { " | Catapult" } else { "" },
+                fm.total_damage * 100.0,
+                score.kills,
+                score.crashes,
             );
         }
     }
 }
 
-pub fn simulate_plane(
+pub fn plane_input(
     keyboard: Res<ButtonInput<KeyCode>>,
     gamepads: Query<&Gamepad>,
     time: Res<Time>,
+    mut plane_query: Query<&mut PlaneComponent, With<Player>>,
+) {
+    for mut plane_component in plane_query.iter_mut() {
+        let plane = &mut plane_component.plane;
+
+        plane.input(&keyboard, time.delta_secs());
+        for gamepad in gamepads.iter() {
+            plane.gamepad_input(gamepad);
+        }
+    }
+}
+
+/// World state every flight model reads each step
+#[derive(SystemParam)]
+pub struct Environment<'w, 's> {
+    atmosphere: Res<'w, Atmosphere>,
+    wind_field: Res<'w, WindField>,
+    terrain: Res<'w, TerrainHeight>,
+    geo: Res<'w, GeoReference>,
+    origin: Res<'w, FloatingOrigin>,
+    carriers: Query<'w, 's, (&'static Carrier, &'static PhysicsTransform), Without<PlaneComponent>>,
+}
+
+pub fn simulate_plane(
+    time: Res<Time>,
+    settings: Res<PhysicsSettings>,
+    environment: Environment,
     mut gizmos: Gizmos,
-    mut plane_query: Query<(&mut Transform, &mut PlaneComponent, Option<&Player>)>,
+    mut crashes: MessageWriter<PlaneCrashed>,
+    mut plane_query: Query<(
+        Entity,
+        &mut PhysicsTransform,
+        &mut AbsolutePosition,
+        &mut PlaneComponent,
+    )>,
 ) {
-    for (mut transform, mut plane_component, is_player) in plane_query.iter_mut() {
+    for (entity, mut physics, mut position, mut plane_component) in plane_query.iter_mut() {
         let plane = &mut plane_component.plane;
         let dt = time.delta_secs();
 
-        if is_player.is_some() {
-            plane.input(&keyboard);
-            for gamepad in gamepads.iter() {
-                plane.gamepad_input(gamepad);
-            }
+        plane.flight_model.atmosphere = *environment.atmosphere;
+        plane
+            .flight_model
+            .wind_field
+            .clone_from(&environment.wind_field);
+        plane.flight_model.terrain.clone_from(&environment.terrain);
+        plane.flight_model.geo = *environment.geo;
+        plane.flight_model.decks.clear();
+        plane.flight_model.decks.extend(
+            environment
+                .carriers
+                .iter()
+                .map(|(carrier, physics)| carrier.deck(&physics.current)),
+        );
+
+        if let Some(integrator) = settings.integrator {
+            plane.flight_model.integrator = integrator;
         }
 
         for (direction, position) in &plane.flight_model.draw_vecs {
@@ -143,6 +276,35 @@ pub fn simulate_plane(
             );
         }
 
-        plane.simulate(dt, &mut transform);
+        let was_crashed = plane.flight_model.crashed;
+        physics.previous = physics.current;
+        plane.simulate(dt, settings.substeps, &mut physics.current);
+        position.0 = environment.origin.to_absolute(physics.current.translation);
+
+        if plane.flight_model.crashed && !was_crashed {
+            crashes.write(PlaneCrashed {
+                entity,
+                position: position.0,
+                impact_speed: plane.flight_model.impact_speed,
+            });
+        }
+    }
+}
+
+pub fn interpolate_plane_transforms(
+    fixed_time: Res<Time<Fixed>>,
+    mut plane_query: Query<(&mut Transform, &PhysicsTransform)>,
+) {
+    let alpha = fixed_time.overstep_fraction();
+
+    for (mut transform, physics) in plane_query.iter_mut() {
+        transform.translation = physics
+            .previous
+            .translation
+            .lerp(physics.current.translation, alpha);
+        transform.rotation = physics
+            .previous
+            .rotation
+            .slerp(physics.current.rotation, alpha);
     }
 }
@@ -3,23 +3,40 @@ use crate::*;
 pub fn spawn_enemy_plane(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    player_entity: Res<PlayerEntity>,
+    player_entity: Option<Res<PlayerEntity>>,
 ) {
     let plane_name = "su-25t";
-    let plane = Plane::new(plane_name, vec3(0.0, 0.0, 200.0));
+    let spawn_velocity = vec3(0.0, 0.0, 200.0);
+    let plane = match Plane::new(plane_name, spawn_velocity) {
+        Ok(plane) => plane,
+        Err(err) => {
+            error!("No enemy aircraft: {err}");
+            return;
+        }
+    };
     let plane_model: Handle<Scene> =
         asset_server.load(format!("aircraft/{}/model.glb#Scene0", plane_name));
 
+    let spawn_transform = Transform {
+        translation: Vec3::new(0.0, 3000.0, -1000.0),
+        rotation: Quat::from_rotation_y(std::f32::consts::PI),
+        ..default()
+    };
+
     commands
         .spawn((
-            Transform {
-                translation: Vec3::new(0.0, 3000.0, -1000.0),
-                rotation: Quat::from_rotation_y(std::f32::consts::PI),
-                ..default()
+            spawn_transform,
+            PhysicsTransform::new(spawn_transform),
+            SpawnPoint {
+                plane_type: plane_name.to_string(),
+                position: spawn_transform.translation.as_dvec3(),
+                rotation: spawn_transform.rotation,
+                velocity: spawn_velocity,
             },
             Visibility::default(),
             PlaneComponent { plane },
-            DogfightAI::new(Some(player_entity.0)),
+            AbsolutePosition(spawn_transform.translation.as_dvec3()),
+            DogfightAI::new(player_entity.map(|player| player.0)),
             Enemy,
         ))
         .with_children(|parent| {
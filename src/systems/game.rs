@@ -1,31 +1,14 @@
 use crate::*;
+use bevy::ecs::query::QueryData;
 use std::f32::consts::PI;
 
+pub const SKY_COLOR: Color = Color::srgb(0.6, 0.8, 1.0);
+
 pub fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
 ) {
-    // Ground plane
-    let grid_size = 50;
-    let size = 1000.0;
-    for x in -grid_size..grid_size {
-        for z in -grid_size..grid_size {
-            let ground_texture_handle = asset_server.load("ground.png");
-            let ground_material = materials.add(StandardMaterial {
-                base_color_texture: Some(ground_texture_handle),
-                perceptual_roughness: 0.9,
-                ..default()
-            });
-            commands.spawn((
-                Mesh3d(meshes.add(Cuboid::new(size, 0.1, size))),
-                MeshMaterial3d(ground_material),
-                Transform::from_xyz(x as f32 * size, 0.0, z as f32 * size),
-            ));
-        }
-    }
-
     // Runway
     let grey_material = materials.add(StandardMaterial {
         base_color: Color::srgb(0.2, 0.2, 0.2),
@@ -63,7 +46,7 @@ pub fn setup(
     ));
 
     // Sky background
-    commands.insert_resource(ClearColor(Color::srgb(0.6, 0.8, 1.0)));
+    commands.insert_resource(ClearColor(SKY_COLOR));
 
     // FPS Text
     commands.spawn((
@@ -81,26 +64,6 @@ pub fn setup(
         },
         FpsText,
     ));
-
-    // Clouds
-    let cloud_altitude = 3000.0; // meters
-    let cloud_amount = 10000;
-    let cloud_size = 400.0; // meters
-    let distibution_radius = 500000.0; // meters
-    // for loop to create clouds in random positions
-    for _ in 0..cloud_amount {
-        let x = (rand::random::<f32>() - 0.5) * distibution_radius;
-        let z = (rand::random::<f32>() - 0.5) * distibution_radius;
-        commands.spawn((
-            Mesh3d(meshes.add(Sphere::new(cloud_size * (rand::random::<f32>() + 0.5)))),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb(1.0, 1.0, 1.0),
-                perceptual_roughness: 1.0,
-                ..default()
-            })),
-            Transform::from_xyz(x, cloud_altitude + (rand::random::<f32>() * 1000.0), z),
-        ));
-    }
 }
 
 pub fn camera_follow(
@@ -125,15 +88,11 @@ pub fn camera_follow(
             transform.translation.z * (right_x * PI / 2.0).cos() * 1.2,
         );
 
+        let Some(player) = plane_query.iter().next() else {
+            return;
+        };
         let mut rng = rand::rng();
-        let shake = plane_query
-            .iter()
-            .next()
-            .unwrap()
-            .plane
-            .flight_model
-            .shake_amplitude
-            * 2.0;
+        let shake = player.plane.flight_model.shake_amplitude * 2.0;
 
         let offset = vec3(
             (rng.random::<f32>() - 0.5) * shake,
@@ -146,6 +105,95 @@ pub fn camera_follow(
     }
 }
 
+const EXPLOSION_TIME: f32 = 2.0; // seconds
+const EXPLOSION_RADIUS: f32 = 15.0; // meters
+
+/// Everything a crash puts back to the spawn point
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct Respawn {
+    spawn: &'static SpawnPoint,
+    plane: &'static mut PlaneComponent,
+    physics: &'static mut PhysicsTransform,
+    transform: &'static mut Transform,
+    position: &'static mut AbsolutePosition,
+    is_player: Has<Player>,
+}
+
+/// Marks the crash site and puts the crashed aircraft back at its spawn point
+pub fn handle_plane_crashes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut crashes: MessageReader<PlaneCrashed>,
+    mut score: ResMut<Score>,
+    origin: Res<FloatingOrigin>,
+    mut plane_query: Query<Respawn>,
+) {
+    for crash in crashes.read() {
+        info!(
+            "{:?} crashed at {:.0} m/s",
+            crash.entity, crash.impact_speed
+        );
+
+        commands.spawn((
+            Mesh3d(meshes.add(Sphere::new(EXPLOSION_RADIUS))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(1.0, 0.5, 0.1),
+                emissive: LinearRgba::rgb(20.0, 6.0, 1.0),
+                ..default()
+            })),
+            Transform::from_translation(origin.to_render(crash.position))
+                .with_scale(Vec3::splat(0.1)),
+            Explosion {
+                timer: Timer::from_seconds(EXPLOSION_TIME, TimerMode::Once),
+            },
+        ));
+
+        let Ok(mut respawn) = plane_query.get_mut(crash.entity) else {
+            continue;
+        };
+        let spawn = respawn.spawn;
+
+        if respawn.is_player {
+            score.crashes += 1;
+        } else {
+            score.kills += 1;
+        }
+
+        let spawn_transform = Transform::from_translation(origin.to_render(spawn.position))
+            .with_rotation(spawn.rotation);
+
+        respawn.plane.plane = match Plane::new(&spawn.plane_type, spawn.velocity) {
+            Ok(plane) => plane,
+            Err(err) => {
+                error!("Cannot respawn {}: {err}", spawn.plane_type);
+                continue;
+            }
+        };
+        *respawn.physics = PhysicsTransform::new(spawn_transform);
+        *respawn.transform = spawn_transform;
+        respawn.position.0 = spawn.position;
+    }
+}
+
+pub fn update_explosions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Explosion, &mut Transform)>,
+) {
+    for (entity, mut explosion, mut transform) in query.iter_mut() {
+        explosion.timer.tick(time.delta());
+        if explosion.timer.is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let grow = explosion.timer.fraction().sqrt();
+        transform.scale = Vec3::splat(0.1 + grow);
+    }
+}
+
 pub fn update_fps(diagnostics: Res<DiagnosticsStore>, mut query: Query<&mut Text, With<FpsText>>) {
     for mut text in &mut query {
         if let Some(fps) = diagnostics.get(&FrameTimeDiagnosticsPlugin::FPS) {
@@ -44,25 +44,27 @@ pub fn rescale(input: f32, min: f32, max: f32) -> f32 {
     }
 }
 
+/// Interval of the increasing breakpoints `xs` around `x`, as the index of its lower end
+/// and the position inside it, 0 at `xs[i]` and 1 at `xs[i + 1]`. Past either end the
+/// position is clamped, or carries on along the outermost interval with `extrapolate`.
+pub fn locate(xs: &[f32], x: f32, extrapolate: bool) -> (usize, f32) {
+    let n = xs.len();
+    if n < 2 {
+        return (0, 0.0);
+    }
+
+    let i = xs.partition_point(|&b| b <= x).clamp(1, n - 1) - 1;
+    let t = (x - xs[i]) / (xs[i + 1] - xs[i]);
+    (i, if extrapolate { t } else { t.clamp(0.0, 1.0) })
+}
+
 #[inline(always)]
 pub fn table_lerp(xs: &[f32], ys: &[f32], x: f32) -> f32 {
-    let n = xs.len();
+    let n = xs.len().min(ys.len());
     if n == 0 {
         return 0.0;
     }
-    if x <= xs[0] {
-        return ys[0];
-    }
-    if x >= xs[n - 1] {
-        return ys[n - 1];
-    }
-
-    for i in 0..n - 1 {
-        if xs[i] <= x && x < xs[i + 1] {
-            let t = (x - xs[i]) / (xs[i + 1] - xs[i]);
-            return ys[i].lerp(ys[i + 1], t);
-        }
-    }
 
-    ys[n - 1]
+    let (i, t) = locate(&xs[..n], x, false);
+    ys[i].lerp(ys[(i + 1).min(n - 1)], t)
 }
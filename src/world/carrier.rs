@@ -0,0 +1,241 @@
+use crate::PhysicsTransform;
+use crate::world::terrain::GroundSample;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+const CARRIER_CONFIG: &str = "assets/carrier/config.toml";
+// Points this far below the deck still land on it rather than falling through to the sea
+const DECK_CONTACT_DEPTH: f32 = 2.0; // meters
+
+/// Arresting wire across the deck
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct Wire {
+    /// Deck z of the wire, aft is positive
+    pub position: f32,
+    /// Meters of cable paid out to stop the aircraft
+    pub runout: f32,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct Catapult {
+    /// Deck x, z where the nosewheel is hooked up
+    pub start: [f32; 2],
+    /// Stroke towards the bow, meters
+    pub length: f32,
+    /// Speed relative to the deck at the end of the stroke, m/s
+    pub end_speed: f32,
+}
+
+/// Ship and deck layout. Deck coordinates have x to starboard, y up from the deck and z aft.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CarrierConfig {
+    /// World x, z the carrier starts at
+    pub position: [f32; 2],
+    /// True heading, degrees
+    pub heading: f32,
+    pub speed: f32,
+    pub deck_length: f32,
+    pub deck_width: f32,
+    /// Deck above the waterline, meters
+    pub deck_height: f32,
+    /// Pitching in the swell, degrees and seconds
+    pub pitch_amplitude: f32,
+    pub pitch_period: f32,
+    /// Heaving in the swell, meters and seconds
+    pub heave_amplitude: f32,
+    pub heave_period: f32,
+    /// Half the length of the wires across the landing area
+    pub wire_half_width: f32,
+    pub wires: Vec<Wire>,
+    pub catapult: Catapult,
+}
+
+#[derive(Component)]
+pub struct Carrier {
+    /// Shared with the deck snapshots, which are taken for every aircraft each step
+    pub config: Arc<CarrierConfig>,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    clock: f32,
+}
+
+/// Snapshot of a carrier deck the flight models land on
+#[derive(Debug, Clone)]
+pub struct Deck {
+    pub config: Arc<CarrierConfig>,
+    /// Ship pose, the deck is `deck_height` above its origin
+    pub transform: Transform,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+}
+
+impl Deck {
+    /// Deck coordinates of a world position
+    pub fn to_deck(&self, pos: Vec3) -> Vec3 {
+        self.transform.rotation.inverse() * (pos - self.transform.translation)
+            - Vec3::Y * self.config.deck_height
+    }
+
+    pub fn to_world(&self, pos: Vec3) -> Vec3 {
+        self.transform.translation
+            + self.transform.rotation * (pos + Vec3::Y * self.config.deck_height)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.transform.rotation * Vec3::Y
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.transform.rotation * Vec3::NEG_Z
+    }
+
+    /// World velocity of the ship at a world position
+    pub fn point_velocity(&self, pos: Vec3) -> Vec3 {
+        self.velocity
+            + self
+                .angular_velocity
+                .cross(pos - self.transform.translation)
+    }
+
+    /// Deck surface under a world position, `None` off the deck or below it
+    pub fn sample(&self, pos: Vec3) -> Option<GroundSample> {
+        let local = self.to_deck(pos);
+        if local.x.abs() > self.config.deck_width / 2.0
+            || local.z.abs() > self.config.deck_length / 2.0
+            || local.y < -DECK_CONTACT_DEPTH
+        {
+            return None;
+        }
+
+        // Height of the deck plane straight below the point
+        let normal = self.up();
+        let on_deck = self.to_world(vec3(local.x, 0.0, local.z));
+        let height = on_deck.y
+            - (normal.x * (pos.x - on_deck.x) + normal.z * (pos.z - on_deck.z)) / normal.y;
+
+        Some(GroundSample {
+            height,
+            normal,
+            velocity: self.point_velocity(pos),
+        })
+    }
+}
+
+impl Carrier {
+    pub fn deck(&self, transform: &Transform) -> Deck {
+        Deck {
+            config: Arc::clone(&self.config),
+            transform: *transform,
+            velocity: self.velocity,
+            angular_velocity: self.angular_velocity,
+        }
+    }
+}
+
+pub fn load_carrier() -> Result<CarrierConfig, String> {
+    let config_str = std::fs::read_to_string(CARRIER_CONFIG)
+        .map_err(|err| format!("{CARRIER_CONFIG}: {err}"))?;
+    toml::from_str(&config_str).map_err(|err| format!("{CARRIER_CONFIG}: {err}"))
+}
+
+pub fn spawn_carrier(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let config = match load_carrier() {
+        Ok(config) => config,
+        Err(err) => {
+            warn!("No carrier: {err}");
+            return;
+        }
+    };
+
+    let [x, z] = config.position;
+    let transform = Transform::from_xyz(x, 0.0, z)
+        .with_rotation(Quat::from_rotation_y(-config.heading.to_radians()));
+
+    let hull_material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.35, 0.37, 0.4),
+        perceptual_roughness: 0.8,
+        ..default()
+    });
+    let deck_material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.15, 0.15, 0.17),
+        perceptual_roughness: 0.9,
+        ..default()
+    });
+    let wire_material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.8, 0.8, 0.8),
+        ..default()
+    });
+
+    let (length, width, height) = (config.deck_length, config.deck_width, config.deck_height);
+    let wire_mesh = meshes.add(Cuboid::new(config.wire_half_width * 2.0, 0.05, 0.1));
+    let wires: Vec<f32> = config.wires.iter().map(|wire| wire.position).collect();
+
+    commands
+        .spawn((
+            transform,
+            PhysicsTransform::new(transform),
+            Visibility::default(),
+            Carrier {
+                config: Arc::new(config),
+                velocity: Vec3::ZERO,
+                angular_velocity: Vec3::ZERO,
+                clock: 0.0,
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Mesh3d(meshes.add(Cuboid::new(width * 0.6, height, length))),
+                MeshMaterial3d(hull_material),
+                Transform::from_xyz(0.0, height / 2.0, 0.0),
+            ));
+            parent.spawn((
+                Mesh3d(meshes.add(Cuboid::new(width, 0.5, length))),
+                MeshMaterial3d(deck_material),
+                Transform::from_xyz(0.0, height - 0.25, 0.0),
+            ));
+            for position in wires {
+                parent.spawn((
+                    Mesh3d(wire_mesh.clone()),
+                    MeshMaterial3d(wire_material.clone()),
+                    Transform::from_xyz(0.0, height + 0.05, position),
+                ));
+            }
+        });
+}
+
+/// Steams the carriers ahead and rocks them in the swell, one physics step at a time
+pub fn move_carriers(time: Res<Time>, mut query: Query<(&mut Carrier, &mut PhysicsTransform)>) {
+    let dt = time.delta_secs();
+
+    for (mut carrier, mut physics) in query.iter_mut() {
+        carrier.clock += dt;
+        let t = carrier.clock;
+        let config = &carrier.config;
+
+        let heading = Quat::from_rotation_y(-config.heading.to_radians());
+        let pitch_rate = TAU / config.pitch_period.max(0.1);
+        let heave_rate = TAU / config.heave_period.max(0.1);
+        let pitch = config.pitch_amplitude.to_radians() * (pitch_rate * t).sin();
+        let pitch_speed = config.pitch_amplitude.to_radians() * pitch_rate * (pitch_rate * t).cos();
+        let heave = config.heave_amplitude * (heave_rate * t).sin();
+        let heave_speed = config.heave_amplitude * heave_rate * (heave_rate * t).cos();
+
+        let velocity = heading * Vec3::NEG_Z * config.speed + Vec3::Y * heave_speed;
+        let angular_velocity = heading * Vec3::X * pitch_speed;
+
+        physics.previous = physics.current;
+        let current = &mut physics.current;
+        current.translation += (heading * Vec3::NEG_Z) * config.speed * dt;
+        current.translation.y = heave;
+        current.rotation = heading * Quat::from_rotation_x(pitch);
+
+        carrier.velocity = velocity;
+        carrier.angular_velocity = angular_velocity;
+    }
+}
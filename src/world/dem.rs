@@ -0,0 +1,266 @@
+use crate::world::geo::GeoReference;
+use crate::world::terrain::Heightmap;
+use bevy::math::dvec3;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+
+const SRTM_VOID: i16 = -32768;
+
+/// Elevation files to build the terrain from instead of a heightmap image
+#[derive(Debug, Deserialize)]
+pub struct DemConfig {
+    /// Directory under `assets/` holding SRTM `.hgt` tiles and ESRI ASCII `.asc` grids
+    pub directory: String,
+    /// Extent east-west and north-south in meters, centred on the world origin
+    pub size: [f32; 2],
+}
+
+/// Geographic elevation grid, rows from north to south
+#[derive(Debug)]
+pub struct DemGrid {
+    /// Latitude and longitude of the north-west sample, degrees
+    pub north: f64,
+    pub west: f64,
+    /// Sample spacing, degrees
+    pub cell: f64,
+    pub columns: usize,
+    pub rows: usize,
+    /// Meters above sea level, NaN where the source has no data
+    pub heights: Vec<f32>,
+}
+
+impl DemGrid {
+    fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    /// Bilinear height, voids are filled from the valid neighbours
+    pub fn height_at(&self, lat: f64, lon: f64) -> Option<f32> {
+        let u = (lon - self.west) / self.cell;
+        let v = (self.north - lat) / self.cell;
+        if u < 0.0 || v < 0.0 || u > (self.columns - 1) as f64 || v > (self.rows - 1) as f64 {
+            return None;
+        }
+
+        let column = (u as usize).min(self.columns - 2);
+        let row = (v as usize).min(self.rows - 2);
+        let (fu, fv) = ((u - column as f64) as f32, (v - row as f64) as f32);
+
+        let corners = [
+            (self.height(column, row), (1.0 - fu) * (1.0 - fv)),
+            (self.height(column + 1, row), fu * (1.0 - fv)),
+            (self.height(column, row + 1), (1.0 - fu) * fv),
+            (self.height(column + 1, row + 1), fu * fv),
+        ];
+        let (sum, weight) = corners
+            .iter()
+            .filter(|(h, _)| !h.is_nan())
+            // Tiny bias so a void sample still takes its height from the others
+            .map(|&(h, w)| (h, w + f32::EPSILON))
+            .fold((0.0, 0.0), |(sum, weight), (h, w)| {
+                (sum + h * w, weight + w)
+            });
+
+        (weight > 0.0).then(|| sum / weight)
+    }
+}
+
+/// Reads an SRTM tile, the south-west corner comes from a name like `N45E006.hgt`
+pub fn load_hgt(path: &Path) -> Result<DemGrid, String> {
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or("invalid file name")?;
+    let (south, west) = parse_tile_name(name)
+        .ok_or_else(|| format!("{name}: expected a tile name like N45E006"))?;
+
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+    if size < 2 || size * size * 2 != bytes.len() {
+        return Err(format!("{} bytes is not a square SRTM tile", bytes.len()));
+    }
+
+    let heights = bytes
+        .chunks_exact(2)
+        .map(|b| match i16::from_be_bytes([b[0], b[1]]) {
+            SRTM_VOID => f32::NAN,
+            h => h as f32,
+        })
+        .collect();
+
+    Ok(DemGrid {
+        north: south + 1.0,
+        west,
+        cell: 1.0 / (size - 1) as f64,
+        columns: size,
+        rows: size,
+        heights,
+    })
+}
+
+fn parse_tile_name(name: &str) -> Option<(f64, f64)> {
+    let name = name.to_ascii_uppercase();
+    let lat_sign = match name.get(0..1)? {
+        "N" => 1.0,
+        "S" => -1.0,
+        _ => return None,
+    };
+    let lon_sign = match name.get(3..4)? {
+        "E" => 1.0,
+        "W" => -1.0,
+        _ => return None,
+    };
+    let lat: f64 = name.get(1..3)?.parse().ok()?;
+    let lon: f64 = name.get(4..7)?.parse().ok()?;
+    Some((lat_sign * lat, lon_sign * lon))
+}
+
+/// Reads an ESRI ASCII grid in geographic (WGS-84 degree) coordinates
+pub fn load_esri_ascii(path: &Path) -> Result<DemGrid, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut tokens = text.split_whitespace().peekable();
+
+    let mut columns = None;
+    let mut rows = None;
+    let mut x = None;
+    let mut y = None;
+    let mut corner = true;
+    let mut cell = None;
+    let mut nodata = None;
+
+    // Header lines are key/value pairs until the first number
+    while let Some(key) = tokens.next_if(|t| t.parse::<f64>().is_err()) {
+        let value: f64 = tokens
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| format!("missing value for {key}"))?;
+        match key.to_ascii_lowercase().as_str() {
+            "ncols" => columns = Some(value as usize),
+            "nrows" => rows = Some(value as usize),
+            "xllcorner" => x = Some(value),
+            "yllcorner" => y = Some(value),
+            "xllcenter" => {
+                x = Some(value);
+                corner = false;
+            }
+            "yllcenter" => {
+                y = Some(value);
+                corner = false;
+            }
+            "cellsize" => cell = Some(value),
+            "nodata_value" => nodata = Some(value as f32),
+            _ => return Err(format!("unknown header key {key}")),
+        }
+    }
+
+    let columns = columns.ok_or("missing ncols")?;
+    let rows = rows.ok_or("missing nrows")?;
+    let cell = cell.ok_or("missing cellsize")?;
+    let (mut west, mut south) = (x.ok_or("missing xllcorner")?, y.ok_or("missing yllcorner")?);
+    if columns < 2 || rows < 2 {
+        return Err("grid needs at least 2x2 samples".to_string());
+    }
+    if corner {
+        // Samples sit at cell centres
+        west += cell / 2.0;
+        south += cell / 2.0;
+    }
+
+    let heights = tokens
+        .map(|t| {
+            let h: f32 = t.parse().map_err(|_| format!("invalid height {t}"))?;
+            Ok(if Some(h) == nodata { f32::NAN } else { h })
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+    if heights.len() != columns * rows {
+        return Err(format!(
+            "expected {} heights for {columns}x{rows}, found {}",
+            columns * rows,
+            heights.len()
+        ));
+    }
+
+    Ok(DemGrid {
+        north: south + (rows - 1) as f64 * cell,
+        west,
+        cell,
+        columns,
+        rows,
+        heights,
+    })
+}
+
+/// Loads every elevation file in the configured directory
+pub fn load_grids(config: &DemConfig) -> Result<Vec<DemGrid>, String> {
+    let directory = format!("assets/{}", config.directory);
+    let entries = std::fs::read_dir(&directory).map_err(|err| format!("{directory}: {err}"))?;
+
+    let mut grids = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let grid = match extension.as_deref() {
+            Some("hgt") => load_hgt(&path),
+            Some("asc") => load_esri_ascii(&path),
+            _ => continue,
+        };
+        grids.push(grid.map_err(|err| format!("{}: {err}", path.display()))?);
+    }
+
+    if grids.is_empty() {
+        return Err(format!("{directory}: no .hgt or .asc files"));
+    }
+    Ok(grids)
+}
+
+/// Resamples the elevation files onto a regular grid around the origin
+pub fn load_heightmap(
+    config: &DemConfig,
+    geo: &GeoReference,
+    spacing: f32,
+) -> Result<Heightmap, String> {
+    let grids = load_grids(config)?;
+
+    let size = Vec2::from(config.size);
+    let columns = (size.x / spacing) as usize + 1;
+    let rows = (size.y / spacing) as usize + 1;
+    let origin = -vec2((columns - 1) as f32, (rows - 1) as f32) * spacing / 2.0;
+
+    let mut missing = 0;
+    let mut heights = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let pos = origin + vec2(column as f32, row as f32) * spacing;
+            let point = geo.to_geodetic(dvec3(pos.x as f64, 0.0, pos.y as f64));
+            let height = grids
+                .iter()
+                .find_map(|grid| grid.height_at(point.latitude, point.longitude));
+            if height.is_none() {
+                missing += 1;
+            }
+            heights.push(height.unwrap_or(0.0));
+        }
+    }
+
+    if missing == heights.len() {
+        return Err(format!(
+            "{}: no elevation data around {:.4}, {:.4}",
+            config.directory, geo.latitude, geo.longitude
+        ));
+    }
+    if missing > 0 {
+        warn!("{missing} terrain samples outside the elevation data, set to sea level");
+    }
+
+    Ok(Heightmap {
+        columns,
+        rows,
+        spacing,
+        origin,
+        heights,
+    })
+}
@@ -0,0 +1,63 @@
+use crate::world::terrain::TerrainHeight;
+use crate::{PhysicsTransform, PlayerEntity};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+// The world is shifted once the player gets this far from the render origin
+const RECENTER_DISTANCE: f32 = 5000.0; // meters
+
+/// Absolute world position of the render origin. Only x and z are shifted, so y
+/// stays the altitude the flight model and atmosphere read from the transforms.
+#[derive(Resource, Debug, Default)]
+pub struct FloatingOrigin {
+    pub offset: DVec3,
+}
+
+impl FloatingOrigin {
+    pub fn to_absolute(&self, pos: Vec3) -> DVec3 {
+        self.offset + pos.as_dvec3()
+    }
+
+    pub fn to_render(&self, pos: DVec3) -> Vec3 {
+        (pos - self.offset).as_vec3()
+    }
+}
+
+/// World position of an aircraft in double precision, unaffected by origin shifts.
+/// Updated after every flight model step.
+#[derive(Component, Debug, Default)]
+pub struct AbsolutePosition(pub DVec3);
+
+/// Moves the render origin to the player once it has flown far enough from it
+pub fn recenter_origin(
+    mut origin: ResMut<FloatingOrigin>,
+    mut terrain: ResMut<TerrainHeight>,
+    player: Option<Res<PlayerEntity>>,
+    mut roots: Query<&mut Transform, Without<ChildOf>>,
+    mut physics: Query<&mut PhysicsTransform>,
+) {
+    let Some(player) = player else {
+        return;
+    };
+    let Ok(player_transform) = roots.get(player.0) else {
+        return;
+    };
+    let distance = player_transform.translation.xz();
+    if distance.length() < RECENTER_DISTANCE {
+        return;
+    }
+
+    // Whole meters keep the accumulated offset exact
+    let shift = vec3(distance.x.round(), 0.0, distance.y.round());
+    origin.offset += shift.as_dvec3();
+    terrain.set_origin(origin.offset);
+
+    // Children follow their parents, so only the roots are moved
+    for mut transform in roots.iter_mut() {
+        transform.translation -= shift;
+    }
+    for mut physics in physics.iter_mut() {
+        physics.previous.translation -= shift;
+        physics.current.translation -= shift;
+    }
+}
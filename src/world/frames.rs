@@ -0,0 +1,163 @@
+// Bevy's frames and the aerospace ones:
+// Bevy world: x east, y up, z south (forward is -Z, true north)
+// Bevy body: x right, y up, z aft
+// NED: x north, y east, z down
+// FRD body: x forward, y right, z down
+use bevy::prelude::*;
+
+pub fn to_ned(v: Vec3) -> Vec3 {
+    vec3(-v.z, v.x, -v.y)
+}
+
+/// Bevy body vector to forward-right-down
+pub fn to_frd(v: Vec3) -> Vec3 {
+    vec3(-v.z, v.x, -v.y)
+}
+
+/// Forward-right-down vector to Bevy body
+pub fn from_frd(v: Vec3) -> Vec3 {
+    vec3(v.y, -v.z, -v.x)
+}
+
+/// Euler angles in degrees, applied heading, then pitch, then bank
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Attitude {
+    /// Nose direction from true north, 0..360 clockwise
+    pub heading: f32,
+    /// Nose above the horizon, -90..90
+    pub pitch: f32,
+    /// Right wing down, -180..180
+    pub bank: f32,
+}
+
+/// Attitude of a body whose Bevy world rotation is `rotation`
+pub fn attitude(rotation: Quat) -> Attitude {
+    // Body FRD to NED rotation, column by column
+    let body_to_ned = Mat3::from_cols(
+        to_ned(rotation * from_frd(Vec3::X)),
+        to_ned(rotation * from_frd(Vec3::Y)),
+        to_ned(rotation * from_frd(Vec3::Z)),
+    );
+    let (heading, pitch, bank) = Quat::from_mat3(&body_to_ned).to_euler(EulerRot::ZYX);
+
+    Attitude {
+        heading: heading.to_degrees().rem_euclid(360.0),
+        pitch: pitch.to_degrees(),
+        bank: bank.to_degrees(),
+    }
+}
+
+/// Body rates p, q, r in rad/s (roll right, pitch up, yaw right) from the Bevy body angular velocity
+pub fn body_rates(angular_velocity: Vec3) -> Vec3 {
+    to_frd(angular_velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NORTH: Vec3 = vec3(0.0, 0.0, -1.0);
+    const EAST: Vec3 = vec3(1.0, 0.0, 0.0);
+
+    /// Bevy world rotation for an attitude in degrees, heading then pitch then bank
+    fn rotation(heading: f32, pitch: f32, bank: f32) -> Quat {
+        Quat::from_rotation_y(-heading.to_radians())
+            * Quat::from_rotation_x(pitch.to_radians())
+            * Quat::from_rotation_z(-bank.to_radians())
+    }
+
+    fn assert_attitude(rotation: Quat, heading: f32, pitch: f32, bank: f32) {
+        let attitude = attitude(rotation);
+        let heading_error = (attitude.heading - heading + 180.0).rem_euclid(360.0) - 180.0;
+        assert!(
+            heading_error.abs() < 1e-3
+                && (attitude.pitch - pitch).abs() < 1e-3
+                && (attitude.bank - bank).abs() < 1e-3,
+            "expected heading {heading}, pitch {pitch}, bank {bank}, found {attitude:?}"
+        );
+    }
+
+    #[test]
+    fn world_axes() {
+        assert!(to_ned(NORTH).abs_diff_eq(Vec3::X, 1e-6));
+        assert!(to_ned(EAST).abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(to_ned(Vec3::NEG_Y).abs_diff_eq(Vec3::Z, 1e-6));
+
+        let v = vec3(1.0, -2.0, 3.0);
+        assert!(from_frd(to_frd(v)).abs_diff_eq(v, 1e-6));
+    }
+
+    #[test]
+    fn body_axes() {
+        // Bevy body: forward is -Z, right is +X, down is -Y
+        assert!(to_frd(Vec3::NEG_Z).abs_diff_eq(Vec3::X, 1e-6));
+        assert!(to_frd(Vec3::X).abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(to_frd(Vec3::NEG_Y).abs_diff_eq(Vec3::Z, 1e-6));
+    }
+
+    #[test]
+    fn level_north() {
+        assert_attitude(Quat::IDENTITY, 0.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn heading_east() {
+        let rotation = rotation(90.0, 0.0, 0.0);
+        assert!((rotation * NORTH).abs_diff_eq(EAST, 1e-6));
+        assert_attitude(rotation, 90.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn pitch_up() {
+        let rotation = rotation(0.0, 30.0, 0.0);
+        let nose = rotation * NORTH;
+        assert!((nose.y - 30f32.to_radians().sin()).abs() < 1e-6);
+        assert_attitude(rotation, 0.0, 30.0, 0.0);
+    }
+
+    #[test]
+    fn bank_right() {
+        let rotation = rotation(0.0, 0.0, 45.0);
+        let right_wing = rotation * Vec3::X;
+        assert!((right_wing.y + 45f32.to_radians().sin()).abs() < 1e-6);
+        assert_attitude(rotation, 0.0, 0.0, 45.0);
+    }
+
+    #[test]
+    fn combined() {
+        assert_attitude(rotation(135.0, -20.0, -60.0), 135.0, -20.0, -60.0);
+        assert_attitude(rotation(300.0, 45.0, 120.0), 300.0, 45.0, 120.0);
+    }
+
+    #[test]
+    fn body_rates_match_the_attitude_change() {
+        let angle = 0.01;
+        let start = rotation(0.0, 0.0, 0.0);
+        // Rotates by each rate over a short time, the angle it drives should follow
+        for (rate, heading, pitch, bank) in [
+            (vec3(1.0, 0.0, 0.0), 0.0, 0.0, angle),
+            (vec3(0.0, 1.0, 0.0), 0.0, angle, 0.0),
+            (vec3(0.0, 0.0, 1.0), angle, 0.0, 0.0),
+        ] {
+            let angular_velocity = from_frd(rate);
+            assert!(body_rates(angular_velocity).abs_diff_eq(rate, 1e-6));
+
+            let rotated = start * Quat::from_scaled_axis(angular_velocity * angle);
+            assert_attitude(
+                rotated,
+                heading.to_degrees(),
+                pitch.to_degrees(),
+                bank.to_degrees(),
+            );
+        }
+    }
+
+    #[test]
+    fn body_rates_in_a_bank() {
+        // Banked 90 degrees right, pulling up turns the nose to the right
+        let start = rotation(0.0, 0.0, 90.0);
+        let angle = 0.01;
+        let rotated = start * Quat::from_scaled_axis(from_frd(vec3(0.0, angle, 0.0)));
+        assert_attitude(rotated, angle.to_degrees(), 0.0, 90.0);
+    }
+}
@@ -0,0 +1,72 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+// WGS-84 ellipsoid
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0; // meters
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+const ECCENTRICITY_SQ: f64 = FLATTENING * (2.0 - FLATTENING);
+
+/// Ties the world frame to the globe. World +X points east, -Z true north and
+/// y is height above mean sea level.
+#[derive(Resource, Debug, Copy, Clone, Default, Deserialize)]
+pub struct GeoReference {
+    /// Latitude of the world origin, degrees north
+    pub latitude: f64,
+    /// Longitude of the world origin, degrees east
+    pub longitude: f64,
+    /// Magnetic declination at the origin, degrees, east positive
+    #[serde(default)]
+    pub magnetic_variation: f32,
+}
+
+/// WGS-84 latitude and longitude in degrees, altitude in meters above mean sea level
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct GeoPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+/// Radius of curvature along the meridian
+fn meridian_radius(latitude: f64) -> f64 {
+    let s = latitude.sin();
+    SEMI_MAJOR_AXIS * (1.0 - ECCENTRICITY_SQ) / (1.0 - ECCENTRICITY_SQ * s * s).powf(1.5)
+}
+
+/// Radius of curvature perpendicular to the meridian
+fn prime_vertical_radius(latitude: f64) -> f64 {
+    let s = latitude.sin();
+    SEMI_MAJOR_AXIS / (1.0 - ECCENTRICITY_SQ * s * s).sqrt()
+}
+
+impl GeoReference {
+    /// Meters per radian of longitude, held at the origin so world east stays along +X
+    fn east_radius(self) -> f64 {
+        let latitude = self.latitude.to_radians();
+        prime_vertical_radius(latitude) * latitude.cos()
+    }
+
+    /// Geodetic position of an absolute world position
+    pub fn to_geodetic(self, pos: DVec3) -> GeoPosition {
+        let origin_lat = self.latitude.to_radians();
+        let north = -pos.z;
+
+        // The meridian radius is taken halfway along the arc, which needs a few iterations
+        let mut latitude = origin_lat + north / meridian_radius(origin_lat);
+        for _ in 0..3 {
+            latitude = origin_lat + north / meridian_radius((origin_lat + latitude) / 2.0);
+        }
+        let longitude = self.longitude + (pos.x / self.east_radius()).to_degrees();
+
+        GeoPosition {
+            latitude: latitude.to_degrees().clamp(-90.0, 90.0),
+            longitude: (longitude + 180.0).rem_euclid(360.0) - 180.0,
+            altitude: pos.y,
+        }
+    }
+
+    pub fn magnetic_heading(self, true_heading: f32) -> f32 {
+        (true_heading - self.magnetic_variation).rem_euclid(360.0)
+    }
+}
@@ -0,0 +1,291 @@
+use crate::Player;
+use crate::world::floating_origin::{AbsolutePosition, FloatingOrigin};
+use crate::world::terrain::{GROUND_TEXTURE_SIZE, TerrainHeight};
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor};
+use bevy::light::NotShadowCaster;
+use bevy::math::{DVec3, dvec3};
+use bevy::mesh::Indices;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::render_resource::PrimitiveTopology;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Flat ground around the terrain, streamed in square chunks
+const GROUND_CHUNK_SIZE: f32 = 4000.0; // meters
+const GROUND_VIEW_CHUNKS: i32 = 10; // radius in chunks
+
+// Clouds are spawned per cell from a seed, so a cell looks the same every time it streams in
+const CLOUD_CELL_SIZE: f32 = 20000.0; // meters
+const CLOUD_VIEW_CELLS: i32 = 2; // radius in cells
+const CLOUDS_PER_CELL: usize = 16;
+const CLOUD_ALTITUDE: f32 = 3000.0; // meters
+const CLOUD_SIZE: f32 = 400.0; // meters
+
+// Chunks are kept until they are this many chunks beyond the view radius, so flying along
+// a chunk border doesn't spawn and despawn them every frame
+const STREAM_HYSTERESIS: i32 = 1;
+
+/// Mesh detail levels, finest first
+pub const LOD_LEVELS: usize = 3;
+/// Distance from the player at which each coarser level takes over
+const LOD_DISTANCES: [f32; LOD_LEVELS - 1] = [6000.0, 15000.0];
+
+/// Farthest the world is drawn, the camera far plane and fog are matched to it
+pub const VIEW_DISTANCE: f32 = GROUND_CHUNK_SIZE * GROUND_VIEW_CHUNKS as f32;
+
+/// Mesh and material handles shared by every streamed entity
+#[derive(Resource)]
+pub struct WorldAssets {
+    pub ground_material: Handle<StandardMaterial>,
+    ground_mesh: Handle<Mesh>,
+    cloud_material: Handle<StandardMaterial>,
+    cloud_meshes: [Handle<Mesh>; LOD_LEVELS],
+}
+
+/// Picks one of its meshes by distance to the player
+#[derive(Component)]
+pub struct LodMeshes {
+    pub levels: [Handle<Mesh>; LOD_LEVELS],
+    /// Point the distance is measured from, relative to the entity
+    pub center: Vec3,
+}
+
+#[derive(Component)]
+pub struct GroundChunk;
+
+#[derive(Component)]
+pub struct CloudCell;
+
+/// Streamed chunks by absolute grid coordinate
+#[derive(Resource, Default)]
+pub struct StreamedChunks {
+    ground: HashMap<IVec2, Entity>,
+    clouds: HashMap<IVec2, Entity>,
+}
+
+pub fn setup_world_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let ground_texture =
+        asset_server.load_with_settings("ground.png", |settings: &mut ImageLoaderSettings| {
+            settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+                address_mode_u: ImageAddressMode::Repeat,
+                address_mode_v: ImageAddressMode::Repeat,
+                ..default()
+            });
+        });
+
+    commands.insert_resource(WorldAssets {
+        ground_material: materials.add(StandardMaterial {
+            base_color_texture: Some(ground_texture),
+            perceptual_roughness: 0.9,
+            ..default()
+        }),
+        ground_mesh: {
+            let tile = Rect::from_center_size(Vec2::ZERO, Vec2::splat(GROUND_CHUNK_SIZE));
+            meshes.add(ground_mesh(tile, &[tile]))
+        },
+        cloud_material: materials.add(StandardMaterial {
+            base_color: Color::srgb(1.0, 1.0, 1.0),
+            perceptual_roughness: 1.0,
+            ..default()
+        }),
+        cloud_meshes: [(32, 18), (16, 9), (8, 5)]
+            .map(|(sectors, stacks)| meshes.add(Sphere::new(1.0).mesh().uv(sectors, stacks))),
+    });
+}
+
+/// Flat ground over `pieces` of `tile`, placed relative to the tile's center and textured at
+/// the same scale as the terrain
+fn ground_mesh(tile: Rect, pieces: &[Rect]) -> Mesh {
+    let mut positions = Vec::with_capacity(pieces.len() * 4);
+    let mut uvs = Vec::with_capacity(pieces.len() * 4);
+    let mut indices = Vec::with_capacity(pieces.len() * 6);
+    for piece in pieces {
+        let first = positions.len() as u32;
+        for corner in [
+            piece.min,
+            vec2(piece.max.x, piece.min.y),
+            vec2(piece.min.x, piece.max.y),
+            piece.max,
+        ] {
+            let local = corner - tile.center();
+            positions.push([local.x, 0.0, local.y]);
+            uvs.push(((corner - tile.min) / GROUND_TEXTURE_SIZE).to_array());
+        }
+        indices.extend([0, 2, 1, 1, 2, 3].map(|i| first + i));
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0, 1.0, 0.0]; positions.len()],
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Parts of `tile` outside `hole`, as up to four rectangles
+fn subtract(tile: Rect, hole: Rect) -> Vec<Rect> {
+    let overlap = tile.intersect(hole);
+    if overlap.is_empty() {
+        return vec![tile];
+    }
+    [
+        // Full-width strips before and after the hole along z
+        Rect::new(tile.min.x, tile.min.y, tile.max.x, overlap.min.y),
+        Rect::new(tile.min.x, overlap.max.y, tile.max.x, tile.max.y),
+        // The sides of the hole between them
+        Rect::new(tile.min.x, overlap.min.y, overlap.min.x, overlap.max.y),
+        Rect::new(overlap.max.x, overlap.min.y, tile.max.x, overlap.max.y),
+    ]
+    .into_iter()
+    .filter(|piece| piece.width() > 0.0 && piece.height() > 0.0)
+    .collect()
+}
+
+/// Grid cells within `radius` cells of `center`
+fn cells_around(center: IVec2, radius: i32) -> impl Iterator<Item = IVec2> {
+    (-radius..=radius)
+        .flat_map(move |x| (-radius..=radius).map(move |z| ivec2(x, z)))
+        .filter(move |offset| offset.length_squared() <= radius * radius)
+        .map(move |offset| center + offset)
+}
+
+fn cell_of(pos: DVec3, size: f32) -> IVec2 {
+    (pos.xz() / size as f64).floor().as_ivec2()
+}
+
+/// Spawns ground and cloud chunks around the player and despawns those left behind
+pub fn stream_world(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<WorldAssets>,
+    terrain: Res<TerrainHeight>,
+    origin: Res<FloatingOrigin>,
+    mut chunks: ResMut<StreamedChunks>,
+    player: Query<&AbsolutePosition, With<Player>>,
+) {
+    let Ok(player) = player.single() else {
+        return;
+    };
+    let pos = player.0;
+    let chunks = &mut *chunks;
+
+    // Ground, cut back to the edge of the terrain where they overlap
+    let center = cell_of(pos, GROUND_CHUNK_SIZE);
+    for cell in cells_around(center, GROUND_VIEW_CHUNKS) {
+        if chunks.ground.contains_key(&cell) {
+            continue;
+        }
+        let middle = (cell.as_dvec2() + 0.5) * GROUND_CHUNK_SIZE as f64;
+        let tile = Rect::from_center_size(middle.as_vec2(), Vec2::splat(GROUND_CHUNK_SIZE));
+        let mesh = match terrain.bounds().map(|bounds| subtract(tile, bounds)) {
+            None => assets.ground_mesh.clone(),
+            Some(pieces) if pieces.is_empty() => continue,
+            Some(pieces) if pieces == [tile] => assets.ground_mesh.clone(),
+            Some(pieces) => meshes.add(ground_mesh(tile, &pieces)),
+        };
+        let entity = commands
+            .spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(assets.ground_material.clone()),
+                Transform::from_translation(origin.to_render(dvec3(middle.x, 0.0, middle.y))),
+                NotShadowCaster,
+                GroundChunk,
+            ))
+            .id();
+        chunks.ground.insert(cell, entity);
+    }
+    despawn_outside(
+        &mut commands,
+        &mut chunks.ground,
+        center,
+        GROUND_VIEW_CHUNKS,
+    );
+
+    // Clouds
+    let center = cell_of(pos, CLOUD_CELL_SIZE);
+    for cell in cells_around(center, CLOUD_VIEW_CELLS) {
+        if chunks.clouds.contains_key(&cell) {
+            continue;
+        }
+        let corner = cell.as_dvec2() * CLOUD_CELL_SIZE as f64;
+        let seed = ((cell.x as u32 as u64) << 32) | cell.y as u32 as u64;
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let entity = commands
+            .spawn((
+                Transform::from_translation(origin.to_render(dvec3(corner.x, 0.0, corner.y))),
+                Visibility::default(),
+                CloudCell,
+            ))
+            .with_children(|parent| {
+                for _ in 0..CLOUDS_PER_CELL {
+                    let x = rng.random::<f32>() * CLOUD_CELL_SIZE;
+                    let z = rng.random::<f32>() * CLOUD_CELL_SIZE;
+                    let y = CLOUD_ALTITUDE + rng.random::<f32>() * 1000.0;
+                    let radius = CLOUD_SIZE * (rng.random::<f32>() + 0.5);
+                    parent.spawn((
+                        Mesh3d(assets.cloud_meshes[0].clone()),
+                        MeshMaterial3d(assets.cloud_material.clone()),
+                        Transform::from_xyz(x, y, z).with_scale(Vec3::splat(radius)),
+                        LodMeshes {
+                            levels: assets.cloud_meshes.clone(),
+                            center: Vec3::ZERO,
+                        },
+                    ));
+                }
+            })
+            .id();
+        chunks.clouds.insert(cell, entity);
+    }
+    despawn_outside(&mut commands, &mut chunks.clouds, center, CLOUD_VIEW_CELLS);
+}
+
+fn despawn_outside(
+    commands: &mut Commands,
+    chunks: &mut HashMap<IVec2, Entity>,
+    center: IVec2,
+    radius: i32,
+) {
+    let keep = radius + STREAM_HYSTERESIS;
+    chunks.retain(|cell, entity| {
+        let inside = (*cell - center).length_squared() <= keep * keep;
+        if !inside {
+            commands.entity(*entity).despawn();
+        }
+        inside
+    });
+}
+
+/// Swaps meshes to the level of detail for their distance to the player
+pub fn update_lods(
+    player: Query<&GlobalTransform, With<Player>>,
+    mut query: Query<(&GlobalTransform, &LodMeshes, &mut Mesh3d)>,
+) {
+    let Ok(player) = player.single() else {
+        return;
+    };
+    let pos = player.translation();
+
+    for (transform, lod, mut mesh) in query.iter_mut() {
+        let distance = transform.transform_point(lod.center).distance(pos);
+        let level = LOD_DISTANCES
+            .iter()
+            .filter(|&&threshold| distance > threshold)
+            .count();
+        if mesh.0 != lod.levels[level] {
+            mesh.0 = lod.levels[level].clone();
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use crate::world::dem::{self, DemConfig};
+use crate::world::geo::GeoReference;
+use crate::world::streaming::{LOD_LEVELS, LodMeshes, WorldAssets};
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+use bevy::math::DVec3;
+use bevy::mesh::Indices;
+use bevy::prelude::*;
+use bevy::render::render_resource::{PrimitiveTopology, TextureFormat};
+use serde::Deserialize;
+use std::sync::Arc;
+
+const TERRAIN_CONFIG: &str = "assets/terrain/config.toml";
+pub const GROUND_TEXTURE_SIZE: f32 = 200.0; // meters covered by one repeat of the ground texture
+// Sample stride of each level of detail
+const LOD_STEPS: [usize; LOD_LEVELS] = [1, 2, 4];
+// Depth of the chunk edge skirts, in cells
+const SKIRT_DEPTH_CELLS: f32 = 4.0;
+
+#[derive(Debug, Deserialize)]
+pub struct TerrainConfig {
+    pub heightmap: Option<String>,
+    pub spacing: f32,
+    #[serde(default)]
+    pub min_height: f32,
+    #[serde(default)]
+    pub max_height: f32,
+    #[serde(default)]
+    pub origin: [f32; 2],
+    pub chunk_size: usize,
+    /// Columns and rows, only needed for headerless .raw files
+    pub raw_size: Option<[usize; 2]>,
+    /// Real elevation data, replaces the heightmap image when present
+    pub dem: Option<DemConfig>,
+    /// Where the world origin is on the globe
+    #[serde(default)]
+    pub geo: GeoReference,
+}
+
+/// Ground height, surface normal and surface velocity at a point
+#[derive(Debug, Copy, Clone)]
+pub struct GroundSample {
+    pub height: f32,
+    pub normal: Vec3,
+    /// Velocity of the surface itself, only moving decks have one
+    pub velocity: Vec3,
+}
+
+impl Default for GroundSample {
+    fn default() -> Self {
+        Self {
+            height: 0.0,
+            normal: Vec3::Y,
+            velocity: Vec3::ZERO,
+        }
+    }
+}
+
+/// Regular grid of heights, columns along +X and rows along +Z
+#[derive(Debug)]
+pub struct Heightmap {
+    pub columns: usize,
+    pub rows: usize,
+    pub spacing: f32,
+    /// World x, z of the first sample
+    pub origin: Vec2,
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn load(config: &TerrainConfig) -> Result<Self, String> {
+        let heightmap = config
+            .heightmap
+            .as_ref()
+            .ok_or("no heightmap or dem configured")?;
+        let path = format!("assets/{heightmap}");
+        let bytes = std::fs::read(&path).map_err(|err| format!("{path}: {err}"))?;
+
+        let (columns, rows, samples) = if path.ends_with(".raw") {
+            let [columns, rows] = config
+                .raw_size
+                .ok_or_else(|| format!("{path}: raw_size is required for .raw heightmaps"))?;
+            if bytes.len() != columns * rows * 2 {
+                return Err(format!(
+                    "{path}: expected {} bytes for {columns}x{rows} 16-bit samples, found {}",
+                    columns * rows * 2,
+                    bytes.len()
+                ));
+            }
+            let samples = bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 / u16::MAX as f32)
+                .collect();
+            (columns, rows, samples)
+        } else {
+            decode_png(&bytes).map_err(|err| format!("{path}: {err}"))?
+        };
+
+        if columns < 2 || rows < 2 {
+            return Err(format!("{path}: heightmap needs at least 2x2 samples"));
+        }
+
+        let range = config.max_height - config.min_height;
+        Ok(Self {
+            columns,
+            rows,
+            spacing: config.spacing,
+            origin: Vec2::from(config.origin),
+            heights: samples
+                .into_iter()
+                .map(|s: f32| config.min_height + s * range)
+                .collect(),
+        })
+    }
+
+    #[inline(always)]
+    fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let size = vec2(
+            (self.columns - 1) as f32 * self.spacing,
+            (self.rows - 1) as f32 * self.spacing,
+        );
+        Rect::from_corners(self.origin, self.origin + size)
+    }
+
+    /// Samples the same triangles the chunk meshes are built from, `None` off the map
+    pub fn sample(&self, x: f32, z: f32) -> Option<GroundSample> {
+        let u = (x - self.origin.x) / self.spacing;
+        let v = (z - self.origin.y) / self.spacing;
+        if u < 0.0 || v < 0.0 || u > (self.columns - 1) as f32 || v > (self.rows - 1) as f32 {
+            return None;
+        }
+
+        let column = (u as usize).min(self.columns - 2);
+        let row = (v as usize).min(self.rows - 2);
+        let (fu, fv) = (u - column as f32, v - row as f32);
+
+        let a = self.height(column, row);
+        let b = self.height(column + 1, row);
+        let c = self.height(column, row + 1);
+        let d = self.height(column + 1, row + 1);
+
+        // Each cell is split along its b-c diagonal
+        let (height, rise_x, rise_z) = if fu + fv <= 1.0 {
+            (a + (b - a) * fu + (c - a) * fv, b - a, c - a)
+        } else {
+            (
+                d + (c - d) * (1.0 - fu) + (b - d) * (1.0 - fv),
+                d - c,
+                d - b,
+            )
+        };
+
+        Some(GroundSample {
+            height,
+            normal: vec3(-rise_x, self.spacing, -rise_z).normalize(),
+            velocity: Vec3::ZERO,
+        })
+    }
+
+    /// Mesh of the cells starting at `column`, `row`, positioned relative to that sample.
+    /// Coarser levels of detail use every `step`th sample.
+    pub fn chunk_mesh(&self, column: usize, row: usize, size: usize, step: usize) -> Mesh {
+        let end_column = (column + size).min(self.columns - 1);
+        let end_row = (row + size).min(self.rows - 1);
+        // Always include the last sample so neighbouring chunks share their edge
+        let samples = |start: usize, end: usize| {
+            (start..end)
+                .step_by(step.max(1))
+                .chain(std::iter::once(end))
+                .collect::<Vec<_>>()
+        };
+        let sample_columns = samples(column, end_column);
+        let sample_rows = samples(row, end_row);
+        let width = sample_columns.len();
+        let height = sample_rows.len();
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        for &r in &sample_rows {
+            for &c in &sample_columns {
+                let local = vec2((c - column) as f32, (r - row) as f32) * self.spacing;
+                let world = self.origin + vec2(c as f32, r as f32) * self.spacing;
+                positions.push([local.x, self.height(c, r), local.y]);
+
+                // Smooth shading from central differences
+                let dx = self.height((c + 1).min(self.columns - 1), r)
+                    - self.height(c.saturating_sub(1), r);
+                let dz = self.height(c, (r + 1).min(self.rows - 1))
+                    - self.height(c, r.saturating_sub(1));
+                normals.push(vec3(-dx, 2.0 * self.spacing, -dz).normalize().to_array());
+                uvs.push((world / GROUND_TEXTURE_SIZE).to_array());
+            }
+        }
+
+        let mut indices = Vec::new();
+        for cell_row in 0..height - 1 {
+            for cell_column in 0..width - 1 {
+                let a = (cell_row * width + cell_column) as u32;
+                let b = a + 1;
+                let c = a + width as u32;
+                let d = c + 1;
+                indices.extend_from_slice(&[a, c, b, b, c, d]);
+            }
+        }
+
+        // Skirts hanging down from the edges hide the cracks between chunks at different
+        // levels of detail. The loop runs along +X on the first row, +Z on the last column,
+        // -X on the last row and -Z on the first column, so the walls face outwards.
+        let perimeter: Vec<usize> = (0..width)
+            .chain((1..height).map(|r| r * width + width - 1))
+            .chain((0..width - 1).rev().map(|c| (height - 1) * width + c))
+            .chain((1..height - 1).rev().map(|r| r * width))
+            .collect();
+        let skirt_start = positions.len() as u32;
+        for &top in &perimeter {
+            let [x, y, z] = positions[top];
+            positions.push([x, y - SKIRT_DEPTH_CELLS * self.spacing, z]);
+            normals.push(normals[top]);
+            uvs.push(uvs[top]);
+        }
+        for i in 0..perimeter.len() {
+            let next = (i + 1) % perimeter.len();
+            let (p, q) = (perimeter[i] as u32, perimeter[next] as u32);
+            let (p_low, q_low) = (skirt_start + i as u32, skirt_start + next as u32);
+            indices.extend_from_slice(&[p, q, q_low, p, q_low, p_low]);
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+/// Grayscale PNG to samples in 0..1
+fn decode_png(bytes: &[u8]) -> Result<(usize, usize, Vec<f32>), String> {
+    let image = Image::from_buffer(
+        bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        false,
+        ImageSampler::Default,
+        RenderAssetUsages::MAIN_WORLD,
+    )
+    .map_err(|err| err.to_string())?;
+
+    let columns = image.width() as usize;
+    let rows = image.height() as usize;
+    let data = image.data.ok_or("image has no pixel data")?;
+
+    let samples = match image.texture_descriptor.format {
+        TextureFormat::R16Uint => data
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]) as f32 / u16::MAX as f32)
+            .collect(),
+        // 8-bit grayscale is expanded to RGBA on decode
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => data
+            .chunks_exact(4)
+            .map(|p| p[0] as f32 / u8::MAX as f32)
+            .collect(),
+        format => return Err(format!("unsupported heightmap format {format:?}")),
+    };
+
+    Ok((columns, rows, samples))
+}
+
+/// Ground height query shared by the flight models. Flat at sea level where there is no map.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TerrainHeight {
+    map: Option<Arc<Heightmap>>,
+    /// Absolute position of the floating origin, queries are relative to it
+    origin: DVec3,
+}
+
+impl TerrainHeight {
+    pub fn new(map: Heightmap) -> Self {
+        Self {
+            map: Some(Arc::new(map)),
+            origin: DVec3::ZERO,
+        }
+    }
+
+    pub fn set_origin(&mut self, origin: DVec3) {
+        self.origin = origin;
+    }
+
+    /// Ground under render space x, z
+    pub fn sample(&self, x: f32, z: f32) -> GroundSample {
+        let x = (self.origin.x + x as f64) as f32;
+        let z = (self.origin.z + z as f64) as f32;
+        self.map
+            .as_ref()
+            .and_then(|map| map.sample(x, z))
+            .unwrap_or_default()
+    }
+
+    /// Area covered by the heightmap in absolute world x, z
+    pub fn bounds(&self) -> Option<Rect> {
+        self.map.as_ref().map(|map| map.bounds())
+    }
+}
+
+#[derive(Component)]
+pub struct TerrainChunk;
+
+pub fn load_config() -> Result<TerrainConfig, String> {
+    let config_str = std::fs::read_to_string(TERRAIN_CONFIG)
+        .map_err(|err| format!("{TERRAIN_CONFIG}: {err}"))?;
+    toml::from_str(&config_str).map_err(|err| format!("{TERRAIN_CONFIG}: {err}"))
+}
+
+pub fn load_heightmap(config: &TerrainConfig) -> Result<Heightmap, String> {
+    match &config.dem {
+        Some(dem) => dem::load_heightmap(dem, &config.geo, config.spacing),
+        None => Heightmap::load(config),
+    }
+}
+
+pub fn setup_terrain(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<WorldAssets>,
+) {
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(err) => {
+            warn!("No terrain, using flat ground: {err}");
+            return;
+        }
+    };
+    commands.insert_resource(config.geo);
+
+    let map = match load_heightmap(&config) {
+        Ok(map) => map,
+        Err(err) => {
+            warn!("No terrain, using flat ground: {err}");
+            return;
+        }
+    };
+
+    let chunk_size = config.chunk_size.max(1);
+    for row in (0..map.rows - 1).step_by(chunk_size) {
+        for column in (0..map.columns - 1).step_by(chunk_size) {
+            let corner = map.origin + vec2(column as f32, row as f32) * map.spacing;
+            let levels =
+                LOD_STEPS.map(|step| meshes.add(map.chunk_mesh(column, row, chunk_size, step)));
+            let half = chunk_size as f32 * map.spacing / 2.0;
+            commands.spawn((
+                Mesh3d(levels[0].clone()),
+                MeshMaterial3d(assets.ground_material.clone()),
+                Transform::from_xyz(corner.x, 0.0, corner.y),
+                LodMeshes {
+                    levels,
+                    center: vec3(half, 0.0, half),
+                },
+                TerrainChunk,
+            ));
+        }
+    }
+
+    commands.insert_resource(TerrainHeight::new(map));
+}